@@ -0,0 +1,441 @@
+//! `#[derive(McConfig)]`, loading typed configs out of MCL with defaults
+//! and validation, see `mcl::config::McConfig` for the attributes.
+//!
+//! ```
+//! use mcl::config::McConfig;
+//! use serde::Deserialize;
+//!
+//! /// Settings of the HTTP server.
+//! #[derive(Deserialize, McConfig)]
+//! struct Server {
+//!     /// Address to listen on.
+//!     #[mcl(default = "0.0.0.0")]
+//!     host: String,
+//!     #[mcl(min = 1, max = 65535)]
+//!     port: u16,
+//!     #[mcl(pattern = "^[a-z]+$")]
+//!     name: Option<String>,
+//! }
+//!
+//! let server = Server::load(b"port 8080").unwrap();
+//! assert_eq!(server.host, "0.0.0.0");
+//!
+//! let errors = Server::load(b"port 0 name \"Web\"").err().unwrap();
+//! assert_eq!(errors.len(), 2);
+//! ```
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse_macro_input;
+use syn::spanned::Spanned;
+use syn::Attribute;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Expr;
+use syn::Fields;
+use syn::GenericArgument;
+use syn::LitStr;
+use syn::PathArguments;
+use syn::Type;
+
+#[proc_macro_derive(McConfig, attributes(mcl))]
+pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// What `#[mcl(...)]` says about a field.
+#[derive(Default)]
+struct FieldAttrs {
+    default: Option<Expr>,
+    optional: bool,
+    min: Option<Expr>,
+    max: Option<Expr>,
+    min_len: Option<Expr>,
+    max_len: Option<Expr>,
+    pattern: Option<LitStr>,
+    nested: bool,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<FieldAttrs> {
+        let mut field = FieldAttrs::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("mcl")) {
+            attr.parse_nested_meta(|meta| {
+                let path = &meta.path;
+
+                if path.is_ident("default") {
+                    field.default = Some(meta.value()?.parse()?);
+                } else if path.is_ident("optional") {
+                    field.optional = true;
+                } else if path.is_ident("min") {
+                    field.min = Some(meta.value()?.parse()?);
+                } else if path.is_ident("max") {
+                    field.max = Some(meta.value()?.parse()?);
+                } else if path.is_ident("min_len") {
+                    field.min_len = Some(meta.value()?.parse()?);
+                } else if path.is_ident("max_len") {
+                    field.max_len = Some(meta.value()?.parse()?);
+                } else if path.is_ident("pattern") {
+                    let pattern: LitStr = meta.value()?.parse()?;
+
+                    if let Err(e) = regex::Regex::new(&pattern.value()) {
+                        return Err(syn::Error::new(pattern.span(), e));
+                    }
+
+                    field.pattern = Some(pattern);
+                } else if path.is_ident("nested") {
+                    field.nested = true;
+                } else {
+                    return Err(meta.error("unknown mcl attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(field)
+    }
+}
+
+/// Doc comment lines of an item, joined back together.
+fn doc(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .filter_map(|a| match &a.meta {
+            syn::Meta::NameValue(nv) => match &nv.value {
+                Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(s),
+                    ..
+                }) => Some(s.value().trim().to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+/// What `#[serde(...)]` says about the struct or a field, as far as the
+/// schema is concerned.
+#[derive(Default)]
+struct SerdeAttrs {
+    rename: Option<LitStr>,
+    rename_all: Option<LitStr>,
+    default: bool,
+}
+
+impl SerdeAttrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<SerdeAttrs> {
+        let mut serde = SerdeAttrs::default();
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("serde")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    serde.rename = deserialize_name(&meta)?.or(serde.rename.take());
+                } else if meta.path.is_ident("rename_all") {
+                    serde.rename_all = deserialize_name(&meta)?.or(serde.rename_all.take());
+                } else if meta.path.is_ident("default") {
+                    serde.default = true;
+                    skip(&meta)?;
+                } else {
+                    skip(&meta)?;
+                }
+
+                Ok(())
+            })?;
+        }
+
+        Ok(serde)
+    }
+}
+
+/// Name given by `rename = "..."` or by the `deserialize` half of
+/// `rename(serialize = "...", deserialize = "...")`.
+fn deserialize_name(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Option<LitStr>> {
+    if meta.input.peek(syn::Token![=]) {
+        return Ok(Some(meta.value()?.parse()?));
+    }
+
+    let mut name = None;
+
+    meta.parse_nested_meta(|inner| {
+        let value: LitStr = inner.value()?.parse()?;
+
+        if inner.path.is_ident("deserialize") {
+            name = Some(value);
+        }
+
+        Ok(())
+    })?;
+
+    Ok(name)
+}
+
+/// Skips the value of a serde attribute the schema does not care about.
+fn skip(meta: &syn::meta::ParseNestedMeta) -> syn::Result<()> {
+    if meta.input.peek(syn::Token![=]) {
+        let _: Expr = meta.value()?.parse()?;
+    } else if meta.input.peek(syn::token::Paren) {
+        let _: TokenStream = meta.input.parse()?;
+    }
+
+    Ok(())
+}
+
+/// `field` renamed following a `rename_all` rule, as serde does.
+fn rename_field(field: &str, rule: &LitStr) -> syn::Result<String> {
+    let pascal = || -> String {
+        field
+            .split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+
+                match chars.next() {
+                    Some(c) => c.to_uppercase().chain(chars).collect(),
+                    None => String::new(),
+                }
+            })
+            .collect()
+    };
+
+    Ok(match rule.value().as_str() {
+        "lowercase" | "snake_case" => field.to_string(),
+        "UPPERCASE" | "SCREAMING_SNAKE_CASE" => field.to_ascii_uppercase(),
+        "PascalCase" => pascal(),
+        "camelCase" => {
+            let pascal = pascal();
+            let mut chars = pascal.chars();
+
+            match chars.next() {
+                Some(c) => c.to_lowercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        }
+        "kebab-case" => field.replace('_', "-"),
+        "SCREAMING-KEBAB-CASE" => field.to_ascii_uppercase().replace('_', "-"),
+        _ => return Err(syn::Error::new(rule.span(), "unknown rename_all rule")),
+    })
+}
+
+/// Key of a field, following `#[serde(rename = "...")]` and the
+/// `rename_all` rule of its struct as serde does.
+fn key(field: &syn::Field, serde: &SerdeAttrs, rename_all: Option<&LitStr>) -> syn::Result<String> {
+    let name = field
+        .ident
+        .as_ref()
+        .map(|i| i.to_string().trim_start_matches("r#").to_string())
+        .unwrap_or_default();
+
+    match (&serde.rename, rename_all) {
+        (Some(rename), _) => Ok(rename.value()),
+        (None, Some(rule)) => rename_field(&name, rule),
+        (None, None) => Ok(name),
+    }
+}
+
+/// Last segment of a type path and its generic arguments.
+fn segment(ty: &Type) -> Option<(String, Vec<&Type>)> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let last = path.path.segments.last()?;
+
+    let args = match &last.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|a| match a {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some((last.ident.to_string(), args))
+}
+
+/// Type the schema expects of a field, unwrapping `Option`.
+fn inner(ty: &Type) -> (&Type, bool) {
+    match segment(ty) {
+        Some((name, args)) if name == "Option" && args.len() == 1 => (args[0], true),
+        _ => (ty, false),
+    }
+}
+
+/// Schema of a Rust type, as far as it can be told from its name.
+fn schema_of(ty: &Type, nested: bool) -> TokenStream {
+    let schema = quote!(::mcl::schema::Schema);
+    let of = |ty: TokenStream| quote!(#schema::of(::mcl::schema::Type::#ty));
+
+    if let Type::Array(array) = ty {
+        let items = schema_of(&array.elem, nested);
+        return quote!(#schema::of(::mcl::schema::Type::List).items(#items));
+    }
+
+    let Some((name, args)) = segment(ty) else {
+        return quote!(#schema::new());
+    };
+
+    match (name.as_str(), args.as_slice()) {
+        (
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+            | "usize",
+            _,
+        ) => of(quote!(Integer)),
+        ("f32" | "f64", _) => of(quote!(Number)),
+        ("bool", _) => of(quote!(Bool)),
+        ("String" | "char" | "PathBuf", _) => of(quote!(String)),
+        ("Option", [inner]) => {
+            let inner = schema_of(inner, nested);
+            quote!(#inner.or(::mcl::schema::Type::Null))
+        }
+        ("Vec" | "VecDeque" | "HashSet" | "BTreeSet", [item]) => {
+            let items = schema_of(item, nested);
+            quote!(#schema::of(::mcl::schema::Type::List).items(#items))
+        }
+        ("HashMap" | "BTreeMap", [_, value]) => {
+            let values = schema_of(value, nested);
+            quote!(#schema::of(::mcl::schema::Type::Table).values(#values))
+        }
+        _ if nested => quote!(<#ty as ::mcl::config::McConfig>::schema()),
+        _ => quote!(#schema::new()),
+    }
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "McConfig needs a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "McConfig can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut deny_unknown = false;
+
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("mcl")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("deny_unknown") {
+                deny_unknown = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown mcl attribute"))
+            }
+        })?;
+    }
+
+    let container = SerdeAttrs::parse(&input.attrs)?;
+
+    let mut schemas = Vec::new();
+    let mut defaults = Vec::new();
+
+    for field in fields {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        let serde = SerdeAttrs::parse(&field.attrs)?;
+        let key = key(field, &serde, container.rename_all.as_ref())?;
+        let (ty, option) = inner(&field.ty);
+
+        let mut schema = schema_of(&field.ty, attrs.nested);
+
+        // serde fills in the fields it has a default for
+        let default = attrs.default.is_some() || serde.default || container.default;
+
+        if option || attrs.optional || default {
+            schema = quote!(#schema.optional());
+        }
+
+        if let Some(min) = &attrs.min {
+            schema = quote!(#schema.min((#min) as f64));
+        }
+
+        if let Some(max) = &attrs.max {
+            schema = quote!(#schema.max((#max) as f64));
+        }
+
+        if let Some(min) = &attrs.min_len {
+            schema = quote!(#schema.min_len(#min));
+        }
+
+        if let Some(max) = &attrs.max_len {
+            schema = quote!(#schema.max_len(#max));
+        }
+
+        if let Some(pattern) = &attrs.pattern {
+            schema = quote!(#schema.pattern(::mcl::regex::Regex::new(#pattern).unwrap()));
+        }
+
+        if let Some(doc) = doc(&field.attrs) {
+            schema = quote!(#schema.with_description(#doc));
+        }
+
+        schemas.push(quote!(.field(#key, #schema)));
+
+        if let Some(default) = &attrs.default {
+            defaults.push(quote! {
+                if !table.contains_key(#key) {
+                    table.insert(#key, ::mcl::config::default_value(#key, #default));
+                }
+            });
+        }
+
+        // defaults of nested lists and maps are left to their items
+        let collection = matches!(
+            segment(ty),
+            Some((name, _)) if matches!(
+                name.as_str(),
+                "Vec" | "VecDeque" | "HashSet" | "BTreeSet" | "HashMap" | "BTreeMap"
+            )
+        );
+
+        if attrs.nested && !collection {
+            defaults.push(quote! {
+                if let Some(inner) = table.get_mut(#key).and_then(::mcl::Value::as_table_mut) {
+                    <#ty as ::mcl::config::McConfig>::fill_defaults(inner);
+                }
+            });
+        }
+    }
+
+    if deny_unknown {
+        schemas.push(quote!(.deny_unknown()));
+    }
+
+    if let Some(doc) = doc(&input.attrs) {
+        schemas.push(quote!(.with_description(#doc)));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::mcl::config::McConfig for #name #ty_generics #where_clause {
+            fn schema() -> ::mcl::schema::Schema {
+                ::mcl::schema::Schema::of(::mcl::schema::Type::Table)
+                    #(#schemas)*
+            }
+
+            fn fill_defaults(table: &mut ::mcl::value::Table) {
+                #(#defaults)*
+            }
+        }
+    })
+}
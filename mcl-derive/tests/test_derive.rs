@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use mcl::config::McConfig;
+use mcl::ErrorKind;
+use serde::Deserialize;
+
+/// Connection to the database.
+#[derive(Debug, Deserialize, McConfig)]
+#[mcl(deny_unknown)]
+struct Database {
+    /// Host name or address.
+    #[mcl(default = "localhost")]
+    host: String,
+    #[mcl(default = 5432, min = 1, max = 65535)]
+    port: u16,
+}
+
+#[derive(Debug, Deserialize, McConfig)]
+struct App {
+    #[mcl(pattern = "^[a-z][a-z0-9-]*$")]
+    name: String,
+    #[mcl(min_len = 1)]
+    hosts: Vec<String>,
+    #[mcl(nested)]
+    database: Database,
+    #[mcl(min = 0.0, max = 1.0)]
+    sample_rate: Option<f64>,
+    #[serde(default)]
+    labels: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, McConfig)]
+#[serde(rename_all = "camelCase")]
+struct Limits {
+    max_connections: u32,
+    #[serde(rename = "timeout")]
+    timeout_secs: u32,
+}
+
+#[test]
+fn test_load() {
+    let app = App::load(b"name \"web\"\nhosts [\"a\"]\ndatabase { port 6543 }").unwrap();
+
+    assert_eq!((app.name.as_str(), app.hosts.len()), ("web", 1));
+    assert_eq!(app.database.host, "localhost");
+    assert_eq!(app.database.port, 6543);
+    assert_eq!(app.sample_rate, None);
+    assert!(app.labels.is_empty());
+}
+
+#[test]
+fn test_load_errors() {
+    let source = "name \"Web\"
+hosts []
+database { port 0 user \"admin\" }
+sample_rate 2
+labels { team 1 }";
+
+    let errors = App::load_str(source).unwrap_err();
+
+    let found: Vec<(&str, usize)> = errors
+        .iter()
+        .map(|e| (e.desc.as_str(), e.loc().unwrap().start().line()))
+        .collect();
+
+    assert_eq!(
+        found,
+        [
+            ("'name' must match the pattern '^[a-z][a-z0-9-]*$'", 1),
+            ("'hosts' must have at least 1 item, found 0", 2),
+            ("'database.port' must be at least 1, found 0", 3),
+            ("unknown key 'database.user'", 3),
+            ("'sample_rate' must be at most 1, found 2", 4),
+            ("'labels.team' must be a string, found a number", 5),
+        ]
+    );
+
+    let errors = App::load(b"hosts [\"a\"] database {}").unwrap_err();
+    assert_eq!(errors[0].desc, "missing required key 'name'");
+    assert_eq!(errors[0].kind, ErrorKind::SchemaViolation);
+}
+
+#[test]
+fn test_serde_attributes() {
+    let limits = Limits::load(b"maxConnections 10 timeout 30").unwrap();
+    assert_eq!((limits.max_connections, limits.timeout_secs), (10, 30));
+
+    let errors = Limits::load(b"max_connections 10 timeout 30").unwrap_err();
+    assert_eq!(errors[0].desc, "missing required key 'maxConnections'");
+
+    let schema = App::schema();
+    let (_, labels) = schema.fields().find(|(key, _)| *key == "labels").unwrap();
+    assert!(labels.is_optional());
+}
+
+#[test]
+fn test_schema_descriptions() {
+    let schema = Database::schema();
+
+    assert_eq!(schema.description(), Some("Connection to the database."));
+
+    let (_, host) = schema.fields().find(|(key, _)| *key == "host").unwrap();
+    assert_eq!(host.description(), Some("Host name or address."));
+    assert!(host.is_optional());
+}
@@ -0,0 +1,86 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::prelude::*;
+
+use crate::parser::Parser;
+use crate::schema::Schema;
+use crate::value::Table;
+use crate::value::Value;
+
+#[cfg(feature = "derive")]
+pub use mcl_derive::McConfig;
+
+/// A config type loaded out of MCL, checked against its schema first so
+/// that every problem is reported at once, each at its location.
+///
+/// Usually derived with the `derive` feature, the type also deriving
+/// serde's `Deserialize`, see `mcl_derive` for an example.
+///
+/// Field attributes, all inside `#[mcl(...)]`:
+///
+/// - `default = expr`, any serializable value used when the key is missing
+/// - `optional`, the key may be missing, implied by `Option` and `default`
+/// - `min = n` and `max = n`, bounds of a number
+/// - `min_len = n` and `max_len = n`, bounds of a length
+/// - `pattern = "regex"`, checked when the macro expands
+/// - `nested`, the type of the field implements `McConfig` itself
+///
+/// Doc comments become the descriptions of the schema, and
+/// `#[mcl(deny_unknown)]` on the struct rejects unknown keys. Keys follow
+/// serde's `rename` and `rename_all`, and serde's `default` makes a field
+/// optional.
+pub trait McConfig: DeserializeOwned {
+    /// Schema of the table the type is loaded from.
+    fn schema() -> Schema;
+
+    /// Adds the default of every field missing from `table`.
+    fn fill_defaults(table: &mut Table);
+
+    fn load_value(mut value: Value) -> std::result::Result<Self, Vec<Error>> {
+        if let Some(table) = value.as_table_mut() {
+            Self::fill_defaults(table);
+        }
+
+        let errors = Self::schema().validate(&value);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        crate::from_value(&value).map_err(|e| vec![e])
+    }
+
+    fn load(source: &[u8]) -> std::result::Result<Self, Vec<Error>> {
+        Self::load_value(crate::parse(source).map_err(|e| vec![e])?)
+    }
+
+    fn load_str(source: &str) -> std::result::Result<Self, Vec<Error>> {
+        Self::load(source.as_bytes())
+    }
+
+    /// Loads the file at `path`, errors naming that file.
+    fn load_file(path: impl AsRef<Path>) -> std::result::Result<Self, Vec<Error>> {
+        let name = path.as_ref().display().to_string();
+        let value = Parser::new().parse_file(&path).map_err(|e| vec![e])?;
+
+        Self::load_value(value)
+            .map_err(|errors| errors.into_iter().map(|e| e.in_file(Some(&name))).collect())
+    }
+}
+
+/// Turns the `default = expr` of the derived field `key` into a value.
+///
+/// # Panics
+///
+/// When the default cannot be serialized, as that is a bug of the struct
+/// rather than of the config being loaded.
+#[doc(hidden)]
+pub fn default_value<T: Serialize>(key: &str, value: T) -> Value {
+    match serde_json::to_value(value) {
+        Ok(value) => Value::from(value),
+        Err(e) => panic!("the default of '{}' cannot be serialized: {}", key, e),
+    }
+}
@@ -0,0 +1,579 @@
+use std::ops::Range;
+
+use serde::Serialize;
+
+use crate::prelude::*;
+
+use crate::lexer::is_identifier;
+use crate::lexer::Lexer;
+use crate::parser::bytes_to_str;
+use crate::parser::check_syntax;
+use crate::parser::create_string;
+use crate::ser::quote_key;
+use crate::ser::Serializer;
+use crate::token::DelimiterKind;
+use crate::token::LiteralKind;
+use crate::token::ReferenceKind;
+use crate::token::TokenKind;
+use crate::token::TriviaKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PieceKind {
+    Whitespace,
+    Newline,
+    Comment,
+    Token,
+}
+
+/// A token or piece of trivia, as a byte range of the source.
+#[derive(Debug, Clone)]
+struct Piece {
+    kind: PieceKind,
+    span: Range<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub enum NodeKind {
+    Scalar,
+    Table(Vec<EntryNode>),
+    List(Vec<Node>),
+}
+
+/// A value of the document along with the bytes it covers.
+#[derive(Debug, Clone)]
+pub struct Node {
+    kind: NodeKind,
+    span: Range<usize>,
+    /// Span of the opening and closing delimiters, `None` for the implicit root.
+    delimiters: Option<(Range<usize>, Range<usize>)>,
+    /// Span of the dotted key leading to a table that only a dotted key
+    /// defines, such as `server.http` in `server.http.port 8080`.
+    prefix: Option<Range<usize>>,
+}
+
+impl Node {
+    pub fn kind(&self) -> &NodeKind {
+        &self.kind
+    }
+
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Node at `path` below this one. A table may be written several
+    /// times with dotted keys, so every entry with the key is tried.
+    fn find(&self, path: &[&str]) -> Option<&Node> {
+        let Some((first, rest)) = path.split_first() else {
+            return Some(self);
+        };
+
+        match &self.kind {
+            NodeKind::Table(entries) => entries
+                .iter()
+                .filter(|e| e.key == *first)
+                .find_map(|e| e.value.find(rest)),
+            NodeKind::List(items) => first
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| items.get(i))
+                .and_then(|item| item.find(rest)),
+            NodeKind::Scalar => None,
+        }
+    }
+
+    /// Span of the whole entry or item at `path` below this one, from the
+    /// start of its key, dotted keys included.
+    fn entry_span(&self, path: &[&str]) -> Option<Range<usize>> {
+        let (first, rest) = path.split_first()?;
+
+        match &self.kind {
+            NodeKind::Table(entries) => {
+                entries
+                    .iter()
+                    .filter(|e| e.key == *first)
+                    .find_map(|e| match rest.is_empty() {
+                        true => Some(e.start..e.value.span.end),
+                        false => e.value.entry_span(rest),
+                    })
+            }
+            NodeKind::List(items) => {
+                let item = items.get(first.parse::<usize>().ok()?)?;
+
+                match rest.is_empty() {
+                    true => Some(item.span()),
+                    false => item.entry_span(rest),
+                }
+            }
+            NodeKind::Scalar => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EntryNode {
+    key: String,
+    key_span: Range<usize>,
+    /// Start of the written entry, before the rest of a dotted key.
+    start: usize,
+    value: Node,
+}
+
+impl EntryNode {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn key_span(&self) -> Range<usize> {
+        self.key_span.clone()
+    }
+
+    pub fn value(&self) -> &Node {
+        &self.value
+    }
+}
+
+fn span(token: &TokenKind) -> Range<usize> {
+    token.loc().start().index()..token.loc().end().index()
+}
+
+/// Builds the tree out of the tokens of a document that already parsed fine.
+struct Builder<'t, 'a> {
+    tokens: Vec<&'t TokenKind<'a>>,
+    index: usize,
+}
+
+impl Builder<'_, '_> {
+    fn token(&self) -> Option<&TokenKind<'_>> {
+        self.tokens.get(self.index).copied()
+    }
+
+    fn root(&mut self, len: usize) -> Node {
+        match self.token() {
+            Some(TokenKind::Delimiter(DelimiterKind::TablePrec(_)))
+            | Some(TokenKind::Delimiter(DelimiterKind::ListPrec(_))) => self.value(),
+
+            Some(TokenKind::Identifier(_)) | Some(TokenKind::Directive(_)) | None => Node {
+                kind: NodeKind::Table(self.entries()),
+                span: 0..len,
+                delimiters: None,
+                prefix: None,
+            },
+
+            Some(_) => Node {
+                kind: NodeKind::List(self.items()),
+                span: 0..len,
+                delimiters: None,
+                prefix: None,
+            },
+        }
+    }
+
+    fn is_closing(&self) -> bool {
+        matches!(
+            self.token(),
+            None | Some(TokenKind::Delimiter(DelimiterKind::TableTerm(_)))
+                | Some(TokenKind::Delimiter(DelimiterKind::ListTerm(_)))
+        )
+    }
+
+    fn entries(&mut self) -> Vec<EntryNode> {
+        let mut entries = Vec::new();
+
+        while !self.is_closing() {
+            let token = self.tokens[self.index];
+
+            let key = match token {
+                TokenKind::Literal(LiteralKind::String(t))
+                | TokenKind::Literal(LiteralKind::TemplateString(t)) => create_string(t.bytes()),
+                token => bytes_to_str(token.token().bytes()).to_string(),
+            };
+
+            self.index += 1;
+
+            let value = self.value();
+
+            entries.push(match token {
+                TokenKind::Identifier(_) => dotted(&key, span(token), value),
+                _ => EntryNode {
+                    key,
+                    key_span: span(token),
+                    start: span(token).start,
+                    value,
+                },
+            });
+        }
+
+        entries
+    }
+
+    fn items(&mut self) -> Vec<Node> {
+        let mut items = Vec::new();
+
+        while !self.is_closing() {
+            items.push(self.value());
+        }
+
+        items
+    }
+
+    fn value(&mut self) -> Node {
+        // anchors and `extends` stay in place when the value is edited
+        while let Some(TokenKind::Reference(ReferenceKind::Anchor(_)))
+        | Some(TokenKind::Reference(ReferenceKind::Extends(_))) = self.token()
+        {
+            self.index += 1;
+        }
+
+        let open = span(self.tokens[self.index]);
+
+        let kind = match self.tokens[self.index] {
+            TokenKind::Delimiter(DelimiterKind::TablePrec(_)) => {
+                self.index += 1;
+                NodeKind::Table(self.entries())
+            }
+            TokenKind::Delimiter(DelimiterKind::ListPrec(_)) => {
+                self.index += 1;
+                NodeKind::List(self.items())
+            }
+            _ => {
+                self.index += 1;
+
+                return Node {
+                    kind: NodeKind::Scalar,
+                    span: open,
+                    delimiters: None,
+                    prefix: None,
+                };
+            }
+        };
+
+        let close = span(self.tokens[self.index]);
+        self.index += 1;
+
+        Node {
+            kind,
+            span: open.start..close.end,
+            delimiters: Some((open, close)),
+            prefix: None,
+        }
+    }
+}
+
+/// Entry of the bare `key` written at `span`, a chain of tables that only
+/// this entry defines when the key is dotted.
+fn dotted(key: &str, span: Range<usize>, value: Node) -> EntryNode {
+    let mut segments = Vec::new();
+    let mut at = span.start;
+
+    for segment in key.split('.') {
+        segments.push((segment, at..at + segment.len()));
+        at += segment.len() + 1;
+    }
+
+    let (last, last_span) = segments.pop().expect("a key has a segment");
+    let end = value.span.end;
+
+    let mut entry = EntryNode {
+        key: last.to_string(),
+        key_span: last_span,
+        start: span.start,
+        value,
+    };
+
+    for (segment, segment_span) in segments.into_iter().rev() {
+        let table = Node {
+            kind: NodeKind::Table(vec![entry]),
+            span: segment_span.end + 1..end,
+            delimiters: None,
+            prefix: Some(span.start..segment_span.end),
+        };
+
+        entry = EntryNode {
+            key: segment.to_string(),
+            key_span: segment_span,
+            start: span.start,
+            value: table,
+        };
+    }
+
+    entry
+}
+
+fn missing(path: &str) -> Error {
+    Error::new(ErrorKind::MissingKey, format!("no value at '{}'", path))
+}
+
+/// Splits `path` into the path of the parent and the last segment.
+fn split(path: &str) -> (Option<&str>, &str) {
+    match path.rsplit_once('.') {
+        Some((parent, last)) => (Some(parent), last),
+        None => (None, path),
+    }
+}
+
+/// Serializes `value` as it would appear after a key.
+fn value_text<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    let mut serializer = Serializer::new(Vec::new()).braced();
+
+    value.serialize(&mut serializer)?;
+
+    Ok(String::from_utf8(serializer.into_inner()).expect("serializer only writes valid UTF-8"))
+}
+
+/// Lossless syntax tree of an MCL document.
+///
+/// Every byte of the source, comments and whitespace included, is kept, so
+/// edits made through [`Document::set`], [`Document::insert`] and
+/// [`Document::remove`] only change the text of the edited entry.
+#[derive(Debug, Clone)]
+pub struct Document {
+    source: String,
+    pieces: Vec<Piece>,
+    root: Node,
+}
+
+impl Document {
+    pub fn parse(source: &str) -> Result<Document> {
+        check_syntax(source.as_bytes())?;
+
+        let tokens = Lexer::new().tokenize_with_trivia(source.as_bytes())?;
+
+        let pieces = tokens
+            .iter()
+            .map(|token| Piece {
+                kind: match token {
+                    TokenKind::Trivia(TriviaKind::Whitespace(_)) => PieceKind::Whitespace,
+                    TokenKind::Trivia(TriviaKind::Newline(_)) => PieceKind::Newline,
+                    TokenKind::Trivia(_) => PieceKind::Comment,
+                    _ => PieceKind::Token,
+                },
+                span: span(token),
+            })
+            .collect();
+
+        let root = Builder {
+            tokens: tokens.iter().filter(|t| !t.is_trivia()).collect(),
+            index: 0,
+        }
+        .root(source.len());
+
+        Ok(Document {
+            source: source.to_string(),
+            pieces,
+            root,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Node at the dot separated `path`, numeric segments indexing lists.
+    pub fn node(&self, path: &str) -> Option<&Node> {
+        self.root.find(&path.split('.').collect::<Vec<_>>())
+    }
+
+    /// Source text of the value at `path`.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.node(path).map(|node| &self.source[node.span()])
+    }
+
+    /// Replaces the value at `path`, which must exist.
+    pub fn set<T: ?Sized + Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let node = self.node(path).ok_or_else(|| missing(path))?;
+        let text = value_text(value)?;
+
+        match node.prefix {
+            // `server.http.port 8080` becomes `server { ... }`, from the dot on
+            Some(_) => self.splice(node.span.start - 1..node.span.end, &format!(" {}", text)),
+            None => self.splice(node.span(), &text),
+        }
+    }
+
+    /// Adds a new entry at `path`, after the last entry of its table.
+    pub fn insert<T: ?Sized + Serialize>(&mut self, path: &str, value: &T) -> Result<()> {
+        let (parent, key) = split(path);
+
+        let table = match parent {
+            Some(parent) => self.node(parent).ok_or_else(|| missing(parent))?,
+            None => &self.root,
+        };
+
+        let entries = match &table.kind {
+            NodeKind::Table(entries) => entries,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidType,
+                    format!("cannot insert '{}', its parent is not a table", path),
+                ))
+            }
+        };
+
+        // the table may also be written elsewhere with dotted keys
+        if entries.iter().any(|e| e.key == key) || self.node(path).is_some() {
+            return Err(Error::new(
+                ErrorKind::DuplicateKey,
+                format!("'{}' already exists", path),
+            ));
+        }
+
+        let entry = match &table.prefix {
+            Some(_) if !is_identifier(key) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidType,
+                    format!(
+                        "cannot insert '{}', a dotted key can only hold bare keys",
+                        path
+                    ),
+                ))
+            }
+            Some(prefix) => format!(
+                "{}.{} {}",
+                &self.source[prefix.clone()],
+                key,
+                value_text(value)?
+            ),
+            None => format!("{} {}", quote_key(key), value_text(value)?),
+        };
+
+        let (at, text) = match (entries.last(), &table.delimiters) {
+            (Some(last), _) => {
+                let end = last.value.span.end;
+
+                match self.line_end(end) {
+                    Some(at) => (at, format!("\n{}{}", self.indent(last.start), entry)),
+                    None => (end, format!(" {}", entry)),
+                }
+            }
+
+            (None, Some((open, close))) => {
+                let inner = open.end..close.start;
+
+                if self.source[inner.clone()].contains('\n') {
+                    let indent = self.indent(close.start);
+                    let unit = if indent.contains('\t') { "\t" } else { "    " };
+
+                    let at = close.start - self.space_before(close.start);
+
+                    match self.source[..at].ends_with('\n') {
+                        true => (at, format!("{}{}{}\n", indent, unit, entry)),
+                        false => (at, format!("\n{}{}{}\n{}", indent, unit, entry, indent)),
+                    }
+                } else {
+                    return self.splice(inner, &format!(" {} ", entry));
+                }
+            }
+
+            (None, None) => {
+                let at = self.source.len();
+
+                match self.source.ends_with('\n') || self.source.is_empty() {
+                    true => (at, format!("{}\n", entry)),
+                    false => (at, format!("\n{}\n", entry)),
+                }
+            }
+        };
+
+        self.splice(at..at, &text)
+    }
+
+    /// Removes the entry or list item at `path`, along with its line when
+    /// nothing else is written on it.
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let span = self
+            .root
+            .entry_span(&path.split('.').collect::<Vec<_>>())
+            .ok_or_else(|| missing(path))?;
+
+        let before = self.space_before(span.start);
+        let after = self.space_after(span.end);
+
+        let line_start = span.start == before || self.source[..span.start - before].ends_with('\n');
+
+        let cut = match self.line_end(span.end) {
+            // the entry has the whole line, remove it with its comment
+            Some(end) if line_start => {
+                let end = (end + 1).min(self.source.len());
+
+                span.start - before..end
+            }
+            // last on a shared line, keep the text before it untouched
+            Some(_) => span.start - before..span.end,
+            None => span.start..span.end + after,
+        };
+
+        self.splice(cut, "")
+    }
+
+    fn splice(&mut self, range: Range<usize>, text: &str) -> Result<()> {
+        let mut source = self.source.clone();
+        source.replace_range(range, text);
+
+        *self = Document::parse(&source)?;
+
+        Ok(())
+    }
+
+    fn piece_at(&self, index: usize) -> Option<usize> {
+        self.pieces
+            .binary_search_by(|p| p.span.start.cmp(&index))
+            .ok()
+    }
+
+    /// Whitespace and comments up to the end of the line of `index`, giving
+    /// the position of the newline, or of the end of input. `None` when a
+    /// token follows on the same line.
+    fn line_end(&self, index: usize) -> Option<usize> {
+        let mut at = index;
+
+        while let Some(i) = self.piece_at(at) {
+            match self.pieces[i].kind {
+                PieceKind::Whitespace | PieceKind::Comment => at = self.pieces[i].span.end,
+                PieceKind::Newline => return Some(at),
+                PieceKind::Token => return None,
+            }
+        }
+
+        Some(at)
+    }
+
+    /// Number of whitespace bytes right before `index`.
+    fn space_before(&self, index: usize) -> usize {
+        let text = &self.source[..index];
+
+        text.len() - text.trim_end_matches([' ', '\t', '\r']).len()
+    }
+
+    /// Number of whitespace bytes right after `index`.
+    fn space_after(&self, index: usize) -> usize {
+        let text = &self.source[index..];
+
+        text.len() - text.trim_start_matches([' ', '\t', '\r']).len()
+    }
+
+    /// Indentation of the line holding `index`.
+    fn indent(&self, index: usize) -> &str {
+        let start = self.source[..index].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.source[start..index];
+
+        &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+    }
+}
+
+impl std::str::FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Document> {
+        Document::parse(s)
+    }
+}
+
+impl std::fmt::Display for Document {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
@@ -0,0 +1,460 @@
+use std::path::Path;
+
+use crate::prelude::*;
+
+use serde::de;
+use serde::de::DeserializeOwned;
+use serde::de::DeserializeSeed;
+use serde::de::IntoDeserializer;
+use serde::de::Visitor;
+
+use crate::parser::Parser;
+use crate::token::Location;
+use crate::value::Entry;
+use crate::value::Number;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Custom, msg.to_string())
+    }
+
+    fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        Error::new(
+            ErrorKind::InvalidType,
+            format!("invalid type: {}, expected {}", unexp, exp),
+        )
+    }
+
+    fn invalid_value(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        Error::new(
+            ErrorKind::InvalidValue,
+            format!("invalid value: {}, expected {}", unexp, exp),
+        )
+    }
+
+    fn invalid_length(len: usize, exp: &dyn de::Expected) -> Self {
+        Error::new(
+            ErrorKind::InvalidValue,
+            format!("invalid length {}, expected {}", len, exp),
+        )
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        Error::new(
+            ErrorKind::InvalidValue,
+            format!(
+                "unknown variant '{}', expected one of {}",
+                variant,
+                expected.join(", ")
+            ),
+        )
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        Error::new(
+            ErrorKind::UnknownField,
+            format!(
+                "unknown field '{}', expected one of {}",
+                field,
+                expected.join(", ")
+            ),
+        )
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::new(
+            ErrorKind::MissingField,
+            format!("missing field '{}'", field),
+        )
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        Error::new(
+            ErrorKind::DuplicateKey,
+            format!("duplicate field '{}'", field),
+        )
+    }
+}
+
+/// Points `error` at `loc` unless it already points somewhere more precise.
+fn locate(mut error: Error, loc: Option<&Location>) -> Error {
+    if error.loc.is_none() {
+        error.loc = loc.copied();
+    }
+
+    error
+}
+
+fn visit_number<'de, V: Visitor<'de>>(n: &Number, visitor: V) -> Result<V::Value> {
+    if let Some(u) = n.as_u64() {
+        visitor.visit_u64(u)
+    } else if let Some(i) = n.as_i64() {
+        visitor.visit_i64(i)
+    } else {
+        match n.as_f64() {
+            Some(f) => visitor.visit_f64(f),
+            None => Err(Error::new(
+                ErrorKind::InvalidNumber,
+                format!("invalid number '{}'", n),
+            )),
+        }
+    }
+}
+
+macro_rules! forward_to_any {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                self.deserialize_any(visitor)
+            }
+        )*
+    };
+}
+
+/// Deserializes Rust values out of a parsed [`Value`], pointing errors at
+/// the location of the offending key or value.
+impl<'de> de::Deserializer<'de> for &Value {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let result = match self.kind() {
+            ValueKind::Null => visitor.visit_unit(),
+            ValueKind::Bool(b) => visitor.visit_bool(*b),
+            ValueKind::Number(n) => visit_number(n, visitor),
+            ValueKind::String(s, _) => visitor.visit_str(s),
+            ValueKind::List(l) => visitor.visit_seq(ListAccess { iter: l.iter() }),
+            ValueKind::Table(t) => visitor.visit_map(TableAccess {
+                iter: t.entries().iter(),
+                value: None,
+            }),
+        };
+
+        result.map_err(|e| locate(e, self.loc()))
+    }
+
+    forward_to_any! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char deserialize_str
+        deserialize_string deserialize_bytes deserialize_byte_buf deserialize_unit
+        deserialize_seq deserialize_map deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.kind() {
+            ValueKind::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        let result = match self.kind() {
+            ValueKind::String(s, _) => visitor.visit_enum(s.as_str().into_deserializer()),
+
+            ValueKind::Table(t) if t.len() == 1 => visitor.visit_enum(Enum {
+                entry: &t.entries()[0],
+            }),
+
+            _ => Err(de::Error::invalid_type(
+                unexpected(self),
+                &"a variant name or a table with a single entry",
+            )),
+        };
+
+        result.map_err(|e| locate(e, self.loc()))
+    }
+}
+
+fn unexpected(value: &Value) -> de::Unexpected<'_> {
+    match value.kind() {
+        ValueKind::Null => de::Unexpected::Unit,
+        ValueKind::Bool(b) => de::Unexpected::Bool(*b),
+        ValueKind::Number(n) => match n.as_f64() {
+            Some(f) => de::Unexpected::Float(f),
+            None => de::Unexpected::Other("number"),
+        },
+        ValueKind::String(s, _) => de::Unexpected::Str(s),
+        ValueKind::List(_) => de::Unexpected::Seq,
+        ValueKind::Table(_) => de::Unexpected::Map,
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for &Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+fn deserialize_key<'de, T: DeserializeSeed<'de>>(entry: &Entry, seed: T) -> Result<T::Value> {
+    seed.deserialize(entry.key().into_deserializer())
+        .map_err(|e| locate(e, entry.key_loc()))
+}
+
+struct TableAccess<'a> {
+    iter: std::slice::Iter<'a, Entry>,
+    value: Option<&'a Value>,
+}
+
+impl<'de> de::MapAccess<'de> for TableAccess<'_> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.iter.next() {
+            Some(entry) => {
+                self.value = Some(entry.value());
+                deserialize_key(entry, seed).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+
+        seed.deserialize(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct ListAccess<'a> {
+    iter: std::slice::Iter<'a, Value>,
+}
+
+impl<'de> de::SeqAccess<'de> for ListAccess<'_> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        match self.iter.next() {
+            Some(value) => seed.deserialize(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Access to an enum written as a single entry table, `{ Variant value }`.
+struct Enum<'a> {
+    entry: &'a Entry,
+}
+
+impl<'de, 'a> de::EnumAccess<'de> for Enum<'a> {
+    type Error = Error;
+    type Variant = &'a Value;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, &'a Value)> {
+        let variant = deserialize_key(self.entry, seed)?;
+
+        Ok((variant, self.entry.value()))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &Value {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
+
+/// Deserializes Rust values out of MCL, parsing the whole input up front
+/// into a [`Value`] so that every error points at its location.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// let mut deserializer = mcl::Deserializer::from_str("port 8080").unwrap();
+/// let value = serde_json::Value::deserialize(&mut deserializer).unwrap();
+///
+/// assert_eq!(value["port"], 8080);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Deserializer {
+    value: Value,
+}
+
+impl Deserializer {
+    /// Deserializer over an already parsed value.
+    pub fn new(value: Value) -> Self {
+        Deserializer { value }
+    }
+
+    pub fn from_slice(v: &[u8]) -> Result<Self> {
+        Ok(Deserializer::new(crate::parse(v)?))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(v: &str) -> Result<Self> {
+        Deserializer::from_slice(v.as_bytes())
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                (&self.value).$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer {
+    type Error = Error;
+
+    forward_to_value! {
+        deserialize_any deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16 deserialize_u32
+        deserialize_u64 deserialize_u128 deserialize_f32 deserialize_f64 deserialize_char
+        deserialize_str deserialize_string deserialize_bytes deserialize_byte_buf
+        deserialize_option deserialize_unit deserialize_seq deserialize_map
+        deserialize_identifier deserialize_ignored_any
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self.value).deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self.value).deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        (&self.value).deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self.value).deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self.value).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        (&self.value).deserialize_enum(name, variants, visitor)
+    }
+}
+
+/// Deserializes an instance of `T` out of a parsed [`Value`].
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T> {
+    T::deserialize(value)
+}
+
+/// Deserializes an instance of `T` from MCL bytes.
+pub fn from_slice<T: DeserializeOwned>(v: &[u8]) -> Result<T> {
+    from_value(&crate::parse(v)?)
+}
+
+/// Deserializes an instance of `T` from MCL text.
+pub fn from_str<T: DeserializeOwned>(v: &str) -> Result<T> {
+    from_slice(v.as_bytes())
+}
+
+/// Deserializes an instance of `T` from the MCL file at `path`, resolving
+/// its includes. The file may start with a byte order mark, as files saved
+/// by some Windows editors do.
+pub fn from_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T> {
+    let name = path.as_ref().display().to_string();
+    let value = Parser::new().parse_file(&path)?;
+
+    from_value(&value).map_err(|e| e.in_file(Some(&name)))
+}
@@ -0,0 +1,87 @@
+use std::fmt::Write;
+
+use crate::prelude::*;
+
+/// Width of `bytes` once printed, with tabs expanded the same way as in
+/// the rendered snippet.
+fn width(bytes: &[u8]) -> usize {
+    String::from_utf8_lossy(bytes)
+        .chars()
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn source_line(source: &[u8], line: usize) -> &[u8] {
+    let text = source.split(|&b| b == b'\n').nth(line - 1).unwrap_or(&[]);
+
+    text.strip_suffix(b"\r").unwrap_or(text)
+}
+
+/// Renders `error` as a rustc style report pointing into `source`.
+///
+/// ```text
+/// error: expected a value, found identifier 'baz'
+///  --> config.mcl:1:11
+///   |
+/// 1 | foo { bar baz }
+///   |           ^^^
+/// ```
+pub fn render(name: &str, source: &[u8], error: &Error) -> String {
+    let mut out = String::new();
+
+    let _ = writeln!(out, "error: {}", error.desc);
+
+    let loc = match error.loc() {
+        Some(loc) => loc,
+        None => {
+            let _ = writeln!(out, " --> {}", name);
+
+            if let Some(help) = error.kind().help() {
+                let _ = writeln!(out, "  = help: {}", help);
+            }
+
+            return out;
+        }
+    };
+
+    let (start, end) = (loc.start(), loc.end());
+
+    let number = start.line().to_string();
+    let gutter = " ".repeat(number.len());
+
+    let text = source_line(source, start.line());
+    let column = (start.column() - 1).min(text.len());
+
+    // spans reaching past the first line are underlined up to its end
+    let stop = if end.line() == start.line() {
+        (end.column() - 1).clamp(column, text.len())
+    } else {
+        text.len()
+    };
+
+    let offset = width(&text[..column]);
+    let length = width(&text[column..stop]).max(1);
+
+    let _ = writeln!(out, "{}--> {}:{}", gutter, name, start);
+    let _ = writeln!(out, "{} |", gutter);
+    let _ = writeln!(
+        out,
+        "{} | {}",
+        number,
+        String::from_utf8_lossy(text).replace('\t', "    ")
+    );
+    let _ = writeln!(
+        out,
+        "{} | {}{}",
+        gutter,
+        " ".repeat(offset),
+        "^".repeat(length)
+    );
+
+    if let Some(help) = error.kind().help() {
+        let _ = writeln!(out, "{} |", gutter);
+        let _ = writeln!(out, "{} = help: {}", gutter, help);
+    }
+
+    out
+}
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+/// Source of the values of `${env:NAME}` placeholders.
+///
+/// The process environment is read through [`ProcessEnv`], tests can use a
+/// `HashMap` or a closure instead.
+pub trait EnvResolver {
+    /// Value of the variable `name`, `None` when it is not set.
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+/// Resolves variables from the environment of the running process.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnv;
+
+impl EnvResolver for ProcessEnv {
+    fn get(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+impl EnvResolver for HashMap<String, String> {
+    fn get(&self, name: &str) -> Option<String> {
+        HashMap::get(self, name).cloned()
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> EnvResolver for F {
+    fn get(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
@@ -1,16 +1,172 @@
+use crate::token::Location;
+
+/// Category of an [`Error`], stable enough to match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A string or template string without its closing quote.
+    UnterminatedString,
+    /// A block comment without its closing `*/`.
+    UnterminatedComment,
+    /// A newline inside a double quoted string.
+    NewlineInString,
+    /// A malformed number literal.
+    InvalidNumber,
+    /// A broken escape in a string, or an unknown one with strict escapes.
+    InvalidEscape,
+    /// A byte that cannot start any token.
+    UnexpectedCharacter,
+    /// Bytes that are not valid UTF-8.
+    InvalidUtf8,
+    /// A token that is not allowed where it appears.
+    UnexpectedToken,
+    /// A `{` or `[` that is never closed.
+    UnclosedDelimiter,
+    /// A `}` or `]` that does not close anything.
+    UnexpectedDelimiter,
+    /// The input ended in the middle of a construct.
+    UnexpectedEof,
+    /// A key was expected but none was found.
+    MissingKey,
+    /// A key was found without a value.
+    MissingValue,
+    /// The same key appears twice in a table.
+    DuplicateKey,
+    /// A value of the wrong type, e.g. a string where a number is expected.
+    InvalidType,
+    /// A value of the right type that is not accepted, e.g. an unknown variant.
+    InvalidValue,
+    /// A key that the target type does not know about.
+    UnknownField,
+    /// A key that the target type requires but the table lacks.
+    MissingField,
+    /// A `${...}` placeholder that is empty or never closed.
+    InvalidPlaceholder,
+    /// A placeholder, alias or `$ref` naming a value that does not exist.
+    UnresolvedReference,
+    /// Values whose placeholders end up referring to themselves.
+    ReferenceCycle,
+    /// An `${env:NAME}` placeholder without default naming an unset variable.
+    MissingEnvVar,
+    /// A directive other than `@include`.
+    UnknownDirective,
+    /// A file that ends up including itself.
+    IncludeCycle,
+    /// A schema that is not written correctly.
+    InvalidSchema,
+    /// A value that does not satisfy its schema.
+    SchemaViolation,
+    /// Reading or writing the underlying stream failed.
+    Io,
+    /// Any other error raised while serializing or deserializing.
+    Custom,
+}
+
+impl ErrorKind {
+    /// Short hint shown under the snippet of a rendered diagnostic.
+    pub fn help(&self) -> Option<&'static str> {
+        match self {
+            ErrorKind::UnterminatedString => Some("add the closing quote"),
+            ErrorKind::UnterminatedComment => Some("close the comment with '*/'"),
+            ErrorKind::NewlineInString => {
+                Some("use a `template string` or the '\\n' escape for multiline text")
+            }
+            ErrorKind::InvalidNumber => {
+                Some("numbers are written like 42, -7, 3.14, 1e6, 0xFF or 1_000")
+            }
+            ErrorKind::InvalidEscape => {
+                Some("escapes are \\n \\r \\t \\\" \\' \\\\ \\` \\$ \\xNN \\uXXXX and \\u{...}")
+            }
+            ErrorKind::UnexpectedCharacter => {
+                Some("quote keys and strings that contain special characters")
+            }
+            ErrorKind::InvalidUtf8 => Some("save the file with the UTF-8 encoding"),
+            ErrorKind::MissingKey | ErrorKind::MissingValue => {
+                Some("every key must be followed by a value")
+            }
+            ErrorKind::DuplicateKey => Some("remove or rename one of the keys"),
+            ErrorKind::UnclosedDelimiter => Some("add the missing closing delimiter"),
+            ErrorKind::UnexpectedDelimiter => Some("remove it or add the matching opening one"),
+            ErrorKind::UnknownField => Some("check the key for typos"),
+            ErrorKind::InvalidPlaceholder => {
+                Some("placeholders are written like ${path.to.key}, escape a literal one as \\${")
+            }
+            ErrorKind::UnresolvedReference => Some("check the path for typos"),
+            ErrorKind::ReferenceCycle => Some("break the cycle by writing one of the values out"),
+            ErrorKind::MissingEnvVar => Some("set it, or give a default with ${env:NAME:-default}"),
+            ErrorKind::UnknownDirective => Some("the only directive is @include \"file.mcl\""),
+            ErrorKind::IncludeCycle => Some("remove one of the @include directives"),
+            ErrorKind::UnexpectedToken
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::InvalidType
+            | ErrorKind::InvalidValue
+            | ErrorKind::MissingField
+            | ErrorKind::InvalidSchema
+            | ErrorKind::SchemaViolation
+            | ErrorKind::Io
+            | ErrorKind::Custom => None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Error {
+    pub kind: ErrorKind,
     pub desc: String,
+    pub loc: Option<Location>,
+    /// File the error was found in, when parsing files.
+    pub file: Option<String>,
 }
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "{:?}", self)
+impl Error {
+    pub fn new(kind: ErrorKind, desc: impl Into<String>) -> Error {
+        Error {
+            kind,
+            desc: desc.into(),
+            loc: None,
+            file: None,
+        }
+    }
+
+    pub fn at(kind: ErrorKind, desc: impl Into<String>, loc: Location) -> Error {
+        Error {
+            kind,
+            desc: desc.into(),
+            loc: Some(loc),
+            file: None,
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn loc(&self) -> Option<&Location> {
+        self.loc.as_ref()
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /// Sets the file of the error unless it already has one.
+    pub fn in_file(mut self, file: Option<&str>) -> Error {
+        if self.file.is_none() {
+            self.file = file.map(str::to_string);
+        }
+
+        self
     }
 }
 
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        self.desc.as_str()
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match (&self.file, &self.loc) {
+            (Some(file), Some(loc)) => write!(f, "{} ({}:{})", self.desc, file, loc),
+            (Some(file), None) => write!(f, "{} ({})", self.desc, file),
+            (None, Some(loc)) => write!(f, "{} ({})", self.desc, loc),
+            (None, None) => write!(f, "{}", self.desc),
+        }
     }
 }
+
+impl std::error::Error for Error {}
@@ -0,0 +1,491 @@
+use crate::prelude::*;
+
+use crate::lexer::Lexer;
+use crate::parser::bytes_to_str;
+use crate::parser::check_syntax;
+use crate::parser::create_string;
+use crate::ser::quote;
+use crate::ser::quote_key;
+use crate::token::DelimiterKind;
+use crate::token::LiteralKind;
+use crate::token::ReferenceKind;
+use crate::token::TokenKind;
+use crate::token::TriviaKind;
+
+/// Layout choices for [`format`].
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Spaces per nesting level.
+    pub indent: usize,
+    /// Line up the values of consecutive scalar entries of a table.
+    pub align_values: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            indent: 4,
+            align_values: false,
+        }
+    }
+}
+
+struct Comment<'a> {
+    text: &'a str,
+    /// Whether an empty line separates the comment from what precedes it.
+    blank: bool,
+}
+
+/// A token along with the comments around it.
+struct Lexeme<'a, 't> {
+    token: &'t TokenKind<'a>,
+    blank: bool,
+    /// Comments on the lines before the token.
+    leading: Vec<Comment<'a>>,
+    /// Comments after the token on the same line.
+    trailing: Vec<Comment<'a>>,
+}
+
+/// Attaches every comment to the token it belongs to, returning the
+/// lexemes along with the comments left at the end of the input.
+fn lexemes<'a, 't>(tokens: &'t [TokenKind<'a>]) -> (Vec<Lexeme<'a, 't>>, Vec<Comment<'a>>) {
+    let mut lexemes: Vec<Lexeme> = Vec::new();
+    let mut pending = Vec::new();
+    let mut newlines = 0;
+
+    for token in tokens {
+        match token {
+            TokenKind::Trivia(TriviaKind::Whitespace(_)) => {}
+
+            TokenKind::Trivia(TriviaKind::Newline(_)) => newlines += 1,
+
+            TokenKind::Trivia(TriviaKind::LineComment(t))
+            | TokenKind::Trivia(TriviaKind::BlockComment(t)) => {
+                let comment = Comment {
+                    text: bytes_to_str(t.bytes()),
+                    blank: newlines > 1,
+                };
+
+                match lexemes.last_mut() {
+                    Some(last) if newlines == 0 && pending.is_empty() => {
+                        last.trailing.push(comment)
+                    }
+                    _ => pending.push(comment),
+                }
+
+                newlines = 0;
+            }
+
+            token => {
+                lexemes.push(Lexeme {
+                    token,
+                    blank: newlines > 1,
+                    leading: std::mem::take(&mut pending),
+                    trailing: Vec::new(),
+                });
+
+                newlines = 0;
+            }
+        }
+    }
+
+    (lexemes, pending)
+}
+
+enum Node {
+    Scalar(usize),
+    Block(Block),
+    /// A value following the anchor or `extends base` at the index.
+    Prefixed(usize, Box<Node>),
+}
+
+/// A table or list, `open` and `close` being `None` for an implicit root.
+struct Block {
+    table: bool,
+    open: Option<usize>,
+    items: Vec<Item>,
+    close: Option<usize>,
+}
+
+struct Item {
+    key: Option<usize>,
+    value: Node,
+}
+
+impl Item {
+    fn first(&self) -> usize {
+        match (self.key, &self.value) {
+            (Some(key), _) => key,
+            (None, Node::Scalar(index)) => *index,
+            (None, Node::Block(block)) => block.open.unwrap_or_default(),
+            (None, Node::Prefixed(prefix, _)) => *prefix,
+        }
+    }
+}
+
+/// Rebuilds the shape of the document, which already parsed fine.
+struct Builder<'l, 'a, 't> {
+    lexemes: &'l [Lexeme<'a, 't>],
+    index: usize,
+}
+
+impl Builder<'_, '_, '_> {
+    fn token(&self) -> Option<&TokenKind<'_>> {
+        self.lexemes.get(self.index).map(|l| l.token)
+    }
+
+    fn root(&mut self) -> Node {
+        match self.token() {
+            Some(TokenKind::Delimiter(DelimiterKind::TablePrec(_)))
+            | Some(TokenKind::Delimiter(DelimiterKind::ListPrec(_))) => self.value(),
+
+            Some(TokenKind::Identifier(_)) | Some(TokenKind::Directive(_)) | None => {
+                Node::Block(self.items(true, None))
+            }
+
+            Some(_) => Node::Block(self.items(false, None)),
+        }
+    }
+
+    fn value(&mut self) -> Node {
+        let index = self.index;
+
+        self.index += 1;
+
+        match self.lexemes[index].token {
+            TokenKind::Delimiter(DelimiterKind::TablePrec(_)) => {
+                Node::Block(self.items(true, Some(index)))
+            }
+            TokenKind::Delimiter(DelimiterKind::ListPrec(_)) => {
+                Node::Block(self.items(false, Some(index)))
+            }
+            TokenKind::Reference(ReferenceKind::Anchor(_))
+            | TokenKind::Reference(ReferenceKind::Extends(_)) => {
+                Node::Prefixed(index, Box::new(self.value()))
+            }
+            _ => Node::Scalar(index),
+        }
+    }
+
+    fn items(&mut self, table: bool, open: Option<usize>) -> Block {
+        let mut items = Vec::new();
+
+        let close = loop {
+            match self.token() {
+                None => break None,
+
+                Some(TokenKind::Delimiter(DelimiterKind::TableTerm(_)))
+                | Some(TokenKind::Delimiter(DelimiterKind::ListTerm(_))) => {
+                    self.index += 1;
+                    break Some(self.index - 1);
+                }
+
+                Some(_) => {
+                    let key = if table {
+                        self.index += 1;
+                        Some(self.index - 1)
+                    } else {
+                        None
+                    };
+
+                    items.push(Item {
+                        key,
+                        value: self.value(),
+                    });
+                }
+            }
+        };
+
+        Block {
+            table,
+            open,
+            items,
+            close,
+        }
+    }
+}
+
+fn key_text(token: &TokenKind) -> String {
+    match token {
+        TokenKind::Literal(LiteralKind::String(t))
+        | TokenKind::Literal(LiteralKind::TemplateString(t)) => {
+            quote_key(&create_string(t.bytes()))
+        }
+        token => bytes_to_str(token.token().bytes()).to_string(),
+    }
+}
+
+fn scalar_text(token: &TokenKind) -> String {
+    match token {
+        // a template string stays one, placeholders or not
+        TokenKind::Literal(LiteralKind::TemplateString(t)) => {
+            format!("`{}`", bytes_to_str(t.bytes()))
+        }
+        TokenKind::Literal(LiteralKind::String(t)) => quote(&create_string(t.bytes())),
+        TokenKind::Reference(ReferenceKind::Ref(t)) => {
+            format!("$ref {}", bytes_to_str(&t.bytes()[4..]).trim_start())
+        }
+        TokenKind::Reference(ReferenceKind::Extends(t)) => {
+            format!("extends {}", bytes_to_str(&t.bytes()[7..]).trim_start())
+        }
+        token => bytes_to_str(token.token().bytes()).to_string(),
+    }
+}
+
+struct Printer<'l, 'a, 't> {
+    lexemes: &'l [Lexeme<'a, 't>],
+    options: &'l FormatOptions,
+    out: String,
+    depth: usize,
+}
+
+impl Printer<'_, '_, '_> {
+    fn indent(&mut self) {
+        let width = self.depth * self.options.indent;
+
+        self.out.extend(std::iter::repeat_n(' ', width));
+    }
+
+    /// Writes comments on their own lines, keeping single empty lines.
+    fn comments(&mut self, comments: &[Comment], mut first: bool) {
+        for comment in comments {
+            if comment.blank && !first {
+                self.out.push('\n');
+            }
+
+            self.indent();
+            self.out.push_str(comment.text);
+            self.out.push('\n');
+
+            first = false;
+        }
+    }
+
+    fn trailing(&mut self, index: usize) {
+        for comment in &self.lexemes[index].trailing {
+            self.out.push(' ');
+            self.out.push_str(comment.text);
+        }
+    }
+
+    /// Whether a list can stay on a single line, `[1 2 3]`.
+    fn inline(&self, block: &Block) -> bool {
+        let quiet =
+            |i: usize| self.lexemes[i].leading.is_empty() && self.lexemes[i].trailing.is_empty();
+
+        !block.table
+            && block
+                .open
+                .is_none_or(|i| self.lexemes[i].trailing.is_empty())
+            && block
+                .close
+                .is_none_or(|i| self.lexemes[i].leading.is_empty())
+            && block.items.iter().all(|item| match item.value {
+                Node::Scalar(index) => quiet(index),
+                Node::Block(_) | Node::Prefixed(..) => false,
+            })
+    }
+
+    /// Index of the last token of `node`, which carries its trailing comments.
+    fn last(node: &Node) -> Option<usize> {
+        match node {
+            Node::Scalar(index) => Some(*index),
+            Node::Block(block) => block.close,
+            Node::Prefixed(_, node) => Self::last(node),
+        }
+    }
+
+    fn value(&mut self, node: &Node) {
+        match node {
+            Node::Scalar(index) => {
+                let text = scalar_text(self.lexemes[*index].token);
+                self.out.push_str(&text);
+            }
+            Node::Block(block) => self.block(block),
+            Node::Prefixed(prefix, node) => {
+                let text = scalar_text(self.lexemes[*prefix].token);
+                self.out.push_str(&text);
+                self.out.push(' ');
+                self.value(node);
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        let (open, close) = if block.table { ('{', '}') } else { ('[', ']') };
+
+        let quiet = block
+            .close
+            .is_none_or(|i| self.lexemes[i].leading.is_empty());
+
+        if block.items.is_empty()
+            && quiet
+            && block
+                .open
+                .is_none_or(|i| self.lexemes[i].trailing.is_empty())
+        {
+            self.out.push(open);
+            self.out.push(close);
+            return;
+        }
+
+        if self.inline(block) {
+            self.out.push(open);
+
+            for (i, item) in block.items.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(' ');
+                }
+
+                self.value(&item.value);
+            }
+
+            self.out.push(close);
+            return;
+        }
+
+        self.out.push(open);
+
+        if let Some(index) = block.open {
+            self.trailing(index);
+        }
+
+        self.out.push('\n');
+        self.depth += 1;
+        self.items(block);
+
+        if let Some(index) = block.close {
+            self.comments(&self.lexemes[index].leading, block.items.is_empty());
+        }
+
+        self.depth -= 1;
+        self.indent();
+        self.out.push(close);
+    }
+
+    /// Width of the keys of the run of scalar entries starting at `from`.
+    fn run_width(&self, items: &[Item], from: usize) -> usize {
+        items[from..]
+            .iter()
+            .enumerate()
+            .take_while(|(i, item)| {
+                matches!(item.value, Node::Scalar(_))
+                    && (*i == 0 || !self.lexemes[item.first()].blank)
+            })
+            .filter_map(|(_, item)| item.key)
+            .map(|key| key_text(self.lexemes[key].token).chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn items(&mut self, block: &Block) {
+        let mut width = 0;
+
+        for (i, item) in block.items.iter().enumerate() {
+            let first = &self.lexemes[item.first()];
+
+            if i > 0 && (first.blank || first.leading.first().is_some_and(|c| c.blank)) {
+                self.out.push('\n');
+            }
+
+            self.comments(&first.leading, true);
+
+            if first.blank && !first.leading.is_empty() {
+                self.out.push('\n');
+            }
+
+            if let Some(key) = item.key {
+                // comments between the key and its value go above the entry
+                self.comments(&self.lexemes[key].trailing, true);
+
+                match &item.value {
+                    Node::Scalar(index) => {
+                        self.comments(&self.lexemes[*index].leading, true);
+                    }
+                    Node::Block(inner) => {
+                        if let Some(open) = inner.open {
+                            self.comments(&self.lexemes[open].leading, true);
+                        }
+                    }
+                    Node::Prefixed(prefix, _) => {
+                        self.comments(&self.lexemes[*prefix].leading, true);
+                    }
+                }
+
+                let text = key_text(self.lexemes[key].token);
+
+                match item.value {
+                    Node::Scalar(_) if self.options.align_values => {
+                        if i == 0
+                            || first.blank
+                            || !matches!(block.items[i - 1].value, Node::Scalar(_))
+                        {
+                            width = self.run_width(&block.items, i);
+                        }
+                    }
+                    _ => width = 0,
+                }
+
+                self.indent();
+                self.out.push_str(&text);
+
+                let padding = width.saturating_sub(text.chars().count()) + 1;
+                self.out.extend(std::iter::repeat_n(' ', padding));
+            } else {
+                self.indent();
+            }
+
+            self.value(&item.value);
+
+            if let Some(last) = Self::last(&item.value) {
+                self.trailing(last);
+            }
+
+            self.out.push('\n');
+        }
+    }
+}
+
+/// Rewrites MCL `source` in the canonical style, one entry per line with
+/// consistent indentation and quoting, keeping every comment.
+pub fn format(source: &[u8], options: &FormatOptions) -> Result<String> {
+    check_syntax(source)?;
+
+    let tokens = Lexer::new().tokenize_with_trivia(source)?;
+    let (lexemes, tail) = lexemes(&tokens);
+
+    let root = Builder {
+        lexemes: &lexemes,
+        index: 0,
+    }
+    .root();
+
+    let mut printer = Printer {
+        lexemes: &lexemes,
+        options,
+        out: String::new(),
+        depth: 0,
+    };
+
+    match &root {
+        Node::Block(block) if block.open.is_none() => printer.items(block),
+        node => {
+            if let Some(first) = lexemes.first() {
+                printer.comments(&first.leading, true);
+            }
+
+            printer.value(node);
+
+            if let Some(last) = Printer::last(node) {
+                printer.trailing(last);
+            }
+
+            printer.out.push('\n');
+        }
+    }
+
+    let first = printer.out.is_empty();
+    printer.comments(&tail, first);
+
+    Ok(printer.out)
+}
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Access to the files named by `@include` directives.
+///
+/// The disk is read through [`OsFileSystem`], tests can back includes with
+/// a `HashMap` from path to content instead.
+pub trait FileSystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>>;
+}
+
+/// Reads files from the disk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OsFileSystem;
+
+impl FileSystem for OsFileSystem {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        std::fs::read(path)
+    }
+}
+
+impl FileSystem for HashMap<PathBuf, String> {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        match self.get(path) {
+            Some(content) => Ok(content.as_bytes().to_vec()),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "no such file",
+            )),
+        }
+    }
+}
+
+/// Path of `target` relative to the directory of the including `file`,
+/// with `.` and `..` components folded away.
+pub fn resolve(file: Option<&Path>, target: &str) -> PathBuf {
+    let joined = match file.and_then(Path::parent) {
+        Some(dir) => dir.join(target),
+        None => PathBuf::from(target),
+    };
+
+    let mut path = PathBuf::new();
+
+    for component in joined.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !path.pop() {
+                    path.push("..");
+                }
+            }
+            component => path.push(component),
+        }
+    }
+
+    path
+}
+
+impl<T: FileSystem + ?Sized> FileSystem for std::rc::Rc<T> {
+    fn read(&self, path: &Path) -> std::io::Result<Vec<u8>> {
+        (**self).read(path)
+    }
+}
@@ -5,14 +5,230 @@ use crate::token::IdentifierKind;
 use crate::token::LiteralKind;
 use crate::token::Location;
 use crate::token::Position;
+use crate::token::ReferenceKind;
 use crate::token::Token;
 use crate::token::TokenKind;
+use crate::token::TriviaKind;
+
+/// Character starting at `index`, if the bytes there make one.
+fn char_at(source: &[u8], index: usize) -> Option<char> {
+    let bytes = source.get(index..)?;
+    let bytes = &bytes[..bytes.len().min(4)];
+
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(s) => s,
+        Err(e) => std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()?,
+    };
+
+    valid.chars().next()
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_ident::is_xid_start(c)
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    unicode_ident::is_xid_continue(c)
+}
+
+/// Length of the name starting at `from`, identifier characters with
+/// dashes between them, as in `max-connections`.
+fn name_len(source: &[u8], from: usize) -> usize {
+    let mut end = from;
+
+    loop {
+        match char_at(source, end) {
+            Some(c) if is_identifier_continue(c) => end += c.len_utf8(),
+            Some('-') => {
+                let dashes = source[end..].iter().take_while(|&&b| b == b'-').count();
+
+                match char_at(source, end + dashes) {
+                    Some(c) if is_identifier_continue(c) => end += dashes,
+                    _ => break,
+                }
+            }
+            _ => break,
+        }
+    }
+
+    end - from
+}
+
+/// Length of the path starting at `from`, names separated with dots.
+fn path_len(source: &[u8], from: usize) -> usize {
+    let mut end = from;
+
+    while let Some(c) = char_at(source, end) {
+        if !(is_identifier_continue(c) || c == '.' || c == '-') {
+            break;
+        }
+
+        end += c.len_utf8();
+    }
+
+    end - from
+}
+
+/// The byte order mark that some editors, mostly on Windows, start UTF-8
+/// files with.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// `source` without its leading byte order mark, if any.
+pub fn strip_bom(source: &[u8]) -> &[u8] {
+    source.strip_prefix(BOM).unwrap_or(source)
+}
+
+/// Checks that `raw`, which starts at `start`, is valid UTF-8, pointing at
+/// the first invalid byte otherwise.
+fn check_utf8(raw: &[u8], start: Position) -> Result<()> {
+    let Err(e) = std::str::from_utf8(raw) else {
+        return Ok(());
+    };
+
+    let valid = &raw[..e.valid_up_to()];
+
+    let (line, column) = match valid.iter().rposition(|&b| b == b'\n') {
+        Some(i) => (
+            start.line() + valid.iter().filter(|&&b| b == b'\n').count(),
+            valid.len() - i,
+        ),
+        None => (start.line(), start.column() + valid.len()),
+    };
+
+    let index = start.index() + valid.len();
+    let len = e.error_len().unwrap_or(raw.len() - valid.len());
+
+    Err(Error::at(
+        ErrorKind::InvalidUtf8,
+        format!("invalid UTF-8 at byte {}", index),
+        Location::new(
+            Position::new(line, column, index),
+            Position::new(line, column + len, index + len),
+        ),
+    ))
+}
+
+/// End of the path in `extends path {`, `from` being right after the
+/// keyword, if the source has that shape.
+fn extends_path(source: &[u8], from: usize) -> Option<usize> {
+    let path = from
+        + source[from..]
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count();
+
+    let end = path + path_len(source, path);
+
+    let open = source[end..].iter().find(|&&b| !b.is_ascii_whitespace());
+
+    (path > from && end > path && open == Some(&b'{')).then_some(end)
+}
+
+/// Whether `key` can be written as a bare identifier instead of a quoted
+/// string, following the same rules as the lexer.
+pub fn is_identifier(key: &str) -> bool {
+    key.chars().next().is_some_and(is_identifier_start)
+        && name_len(key.as_bytes(), 0) == key.len()
+        && !matches!(key, "true" | "false" | "null")
+}
+
+/// Value of `digits` written in hexadecimal, if they all are hex digits.
+fn hex(digits: &[u8]) -> Option<u32> {
+    digits
+        .iter()
+        .all(u8::is_ascii_hexdigit)
+        .then(|| u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok())
+        .flatten()
+}
+
+/// Character written by the escape at the start of `bytes`, which comes
+/// right after a `\`, and the number of bytes it takes.
+///
+/// Errors describe why the escape is unknown or broken, along with the
+/// number of bytes to point at.
+pub fn unescape(bytes: &[u8]) -> std::result::Result<(char, usize), (String, usize)> {
+    match bytes.first() {
+        Some(b'n') => Ok(('\n', 1)),
+        Some(b'r') => Ok(('\r', 1)),
+        Some(b't') => Ok(('\t', 1)),
+        Some(&b @ (b'"' | b'\'' | b'\\' | b'`' | b'$' | b'\n')) => Ok((b as char, 1)),
+
+        Some(b'x') => match bytes.get(1..3).and_then(hex) {
+            Some(n) if n <= 0x7F => Ok((n as u8 as char, 3)),
+            Some(_) => Err((
+                "hex escapes only go up to '\\x7F', use '\\u{...}' above it".to_string(),
+                3,
+            )),
+            None => Err(("hex escape must have two hex digits".to_string(), 1)),
+        },
+
+        Some(b'u') if bytes.get(1) == Some(&b'{') => {
+            let Some(close) = bytes.iter().take(10).position(|&b| b == b'}') else {
+                return Err(("unicode escape is missing its closing '}'".to_string(), 2));
+            };
+
+            let digits = &bytes[2..close];
+
+            let n = match hex(digits) {
+                Some(n) if !digits.is_empty() && digits.len() <= 6 => n,
+                _ => {
+                    return Err((
+                        "unicode escape must have one to six hex digits".to_string(),
+                        close + 1,
+                    ))
+                }
+            };
+
+            match char::from_u32(n) {
+                Some(c) => Ok((c, close + 1)),
+                None => Err((format!("'{:X}' is not a unicode character", n), close + 1)),
+            }
+        }
+
+        Some(b'u') => {
+            let Some(n) = bytes.get(1..5).and_then(hex) else {
+                return Err((
+                    "unicode escape must be written '\\uXXXX' or '\\u{...}'".to_string(),
+                    1,
+                ));
+            };
+
+            if let Some(c) = char::from_u32(n) {
+                return Ok((c, 5));
+            }
+
+            // a high surrogate followed by a low one, as in JSON
+            let low = match bytes.get(5..7) {
+                Some(b"\\u") => bytes.get(7..11).and_then(hex),
+                _ => None,
+            };
+
+            match low {
+                Some(low) if (0xD800..0xDC00).contains(&n) && (0xDC00..0xE000).contains(&low) => {
+                    let c = 0x10000 + ((n - 0xD800) << 10) + (low - 0xDC00);
+                    Ok((char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER), 11))
+                }
+                _ => Err((format!("unpaired surrogate '\\u{:X}'", n), 5)),
+            }
+        }
+
+        Some(_) => match char_at(bytes, 0) {
+            Some(c) => Err((format!("unknown escape '\\{}'", c), c.len_utf8())),
+            None => Err(("unknown escape".to_string(), 1)),
+        },
+
+        None => Err(("escape is missing its character".to_string(), 0)),
+    }
+}
 
 #[derive(Default)]
 pub struct Lexer {
     index: usize,
     column: usize,
     line: usize,
+    /// Whether unknown and broken escapes are errors instead of being kept
+    /// as they are.
+    strict: bool,
 }
 
 impl Lexer {
@@ -43,37 +259,117 @@ impl Lexer {
         Position::new(self.line(), self.column(), self.index())
     }
 
+    fn location(&self, start: Position) -> Location {
+        Location::new(start, self.position())
+    }
+
+    /// Location of the `len` bytes starting at the cursor.
+    fn location_at(&self, len: usize) -> Location {
+        let start = self.position();
+
+        Location::new(
+            start,
+            Position::new(start.line(), start.column() + len, start.index() + len),
+        )
+    }
+
+    /// Location of the single byte under the cursor.
+    fn here(&self) -> Location {
+        let start = self.position();
+
+        Location::new(
+            start,
+            Position::new(start.line(), start.column() + 1, start.index() + 1),
+        )
+    }
+
+    fn delimiter<'a>(&mut self, source: &'a [u8]) -> Token<'a> {
+        let start = self.position();
+
+        self.next();
+
+        Token::new(self.location(start), &source[start.index()..self.index()])
+    }
+
+    /// Skips the escape whose `\` is under the cursor, leaving an escaped
+    /// newline to the string. An unknown or broken escape is an error in
+    /// strict mode, its backslash alone being skipped otherwise.
+    fn escape(&mut self, source: &[u8]) -> Option<Error> {
+        let start = self.position();
+
+        self.next(); // skip backslash
+
+        let rest = &source[self.index()..];
+
+        match unescape(rest) {
+            Ok(('\n', _)) => None,
+            Ok((_, len)) => {
+                (0..len).for_each(|_| self.next());
+                None
+            }
+            Err(_) if rest.is_empty() => None,
+            Err((desc, len)) => self.strict.then(|| {
+                Error::at(
+                    ErrorKind::InvalidEscape,
+                    desc,
+                    Location::new(
+                        start,
+                        Position::new(
+                            start.line(),
+                            start.column() + 1 + len,
+                            start.index() + 1 + len,
+                        ),
+                    ),
+                )
+            }),
+        }
+    }
+
     fn string<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
+        let start = self.position();
+
         self.next(); // skip opening double quotes
 
-        let start = self.position();
+        let content = self.index();
+        let mut invalid = None;
 
         while let Some(&b) = source.get(self.index()) {
             match b {
                 b'"' => break,
+                b'\\' => {
+                    // an escaped quote does not end the string
+                    let error = self.escape(source);
+                    invalid = invalid.or(error);
+                }
                 b'\n' => {
-                    return Err(Error {
-                        desc: "cannot use newline character in strings".to_string(),
-                    })
+                    return Err(Error::at(
+                        ErrorKind::NewlineInString,
+                        "cannot use newline character in strings",
+                        self.here(),
+                    ))
                 }
                 _ => self.next(),
             }
         }
 
         if source.get(self.index()).is_none() {
-            return Err(Error {
-                desc: format!("unterminated string ({}:{})", start.line(), start.column()),
-            });
+            return Err(Error::at(
+                ErrorKind::UnterminatedString,
+                "unterminated string",
+                self.location(start),
+            ));
         }
 
-        let end = self.position();
+        let raw = &source[content..self.index()];
 
         self.next(); // skip closing double quotes
 
-        let raw = &source[start.index()..end.index()];
+        if let Some(e) = invalid {
+            return Err(e);
+        }
 
         Ok(TokenKind::Literal(LiteralKind::String(Token::new(
-            Location::new(start, end),
+            self.location(start),
             raw,
         ))))
     }
@@ -81,97 +377,370 @@ impl Lexer {
     fn identifier<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
         let start = self.position();
 
-        while let Some(b) = source.get(self.index()) {
-            if !(b.is_ascii_alphanumeric() || *b == b'_') {
-                break;
-            }
+        let mut end = self.index() + name_len(source, self.index());
 
-            self.next();
+        // a dotted key, `server.http.port`
+        while source.get(end) == Some(&b'.')
+            && char_at(source, end + 1).is_some_and(is_identifier_start)
+        {
+            end += 1 + name_len(source, end + 1);
         }
 
-        let end = self.position();
+        while self.index() < end {
+            self.next();
+        }
 
-        let raw = &source[start.index()..end.index()];
+        let raw = &source[start.index()..self.index()];
+        let token = Token::new(self.location(start), raw);
 
         match raw {
-            b"true" => Ok(TokenKind::Literal(LiteralKind::True)),
+            b"true" => Ok(TokenKind::Literal(LiteralKind::True(token))),
+
+            b"false" => Ok(TokenKind::Literal(LiteralKind::False(token))),
+
+            b"null" => Ok(TokenKind::Literal(LiteralKind::Null(token))),
+
+            b"extends" => match extends_path(source, self.index()) {
+                Some(end) => {
+                    while self.index() < end {
+                        self.next();
+                    }
+
+                    Ok(TokenKind::Reference(ReferenceKind::Extends(Token::new(
+                        self.location(start),
+                        &source[start.index()..end],
+                    ))))
+                }
+                // a key named `extends`
+                None => Ok(TokenKind::Identifier(IdentifierKind::String(token))),
+            },
 
-            b"false" => Ok(TokenKind::Literal(LiteralKind::False)),
+            _ => Ok(TokenKind::Identifier(IdentifierKind::String(token))),
+        }
+    }
 
-            b"null" => Ok(TokenKind::Literal(LiteralKind::Null)),
+    fn directive<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
+        let start = self.position();
 
-            _ => Ok(TokenKind::Identifier(IdentifierKind::String(Token::new(
-                Location::new(start, end),
-                raw,
-            )))),
+        self.next(); // skip at sign
+
+        for _ in 0..name_len(source, self.index()) {
+            self.next();
         }
+
+        if self.index() == start.index() + 1 {
+            return Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                "expected a directive name after '@'",
+                self.location(start),
+            ));
+        }
+
+        Ok(TokenKind::Directive(Token::new(
+            self.location(start),
+            &source[start.index()..self.index()],
+        )))
+    }
+
+    /// `&name` or `*name`.
+    fn anchor<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
+        let start = self.position();
+        let sigil = source[start.index()];
+
+        self.next(); // skip sigil
+
+        for _ in 0..name_len(source, self.index()) {
+            self.next();
+        }
+
+        if self.index() == start.index() + 1 {
+            return Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                format!(
+                    "expected {} name after '{}'",
+                    if sigil == b'&' {
+                        "an anchor"
+                    } else {
+                        "an alias"
+                    },
+                    sigil as char
+                ),
+                self.location(start),
+            ));
+        }
+
+        let token = Token::new(self.location(start), &source[start.index()..self.index()]);
+
+        Ok(TokenKind::Reference(match sigil {
+            b'&' => ReferenceKind::Anchor(token),
+            _ => ReferenceKind::Alias(token),
+        }))
+    }
+
+    /// `$ref path.to.key`, a single token spanning the keyword and the path.
+    fn reference<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
+        let start = self.position();
+
+        if !source[start.index()..].starts_with(b"$ref") {
+            return Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                "expected '$ref'",
+                self.here(),
+            ));
+        }
+
+        for _ in 0..4 {
+            self.next();
+        }
+
+        let keyword = self.index();
+
+        while let Some(b' ' | b'\t') = source.get(self.index()) {
+            self.next();
+        }
+
+        let path = self.index();
+
+        if path == keyword {
+            return Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                "expected a space between '$ref' and its path",
+                self.here(),
+            ));
+        }
+
+        for _ in 0..path_len(source, path) {
+            self.next();
+        }
+
+        if self.index() == path {
+            return Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                "expected a path after '$ref'",
+                self.location(start),
+            ));
+        }
+
+        Ok(TokenKind::Reference(ReferenceKind::Ref(Token::new(
+            self.location(start),
+            &source[start.index()..self.index()],
+        ))))
     }
 
     fn number<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
-        let mut point = false;
-        let mut zero = false;
+        let start = self.position();
 
-        if let Some(&b) = source.get(self.index()) {
-            if b == b'0' {
-                zero = true;
+        if let Some(b'+' | b'-') = source.get(self.index()) {
+            self.next(); // skip sign
+        }
+
+        let radix = match source.get(self.index()..self.index() + 2) {
+            Some(b"0x") => Some((16, "hexadecimal")),
+            Some(b"0o") => Some((8, "octal")),
+            Some(b"0b") => Some((2, "binary")),
+            _ => None,
+        };
+
+        match radix {
+            Some((radix, name)) => {
+                self.next();
+                self.next(); // skip prefix
+
+                self.radix_digits(source, radix, name)?;
             }
+            None => self.decimal(source, start)?,
         }
 
-        let start = self.position();
+        let raw = &source[start.index()..self.index()];
+
+        Ok(TokenKind::Literal(LiteralKind::Number(Token::new(
+            self.location(start),
+            raw,
+        ))))
+    }
 
-        self.next(); // skip opening first digit
+    /// Digits in `radix`, with single `_` separators between them, returning
+    /// how many digits were read.
+    fn digits(&mut self, source: &[u8], radix: u32) -> Result<usize> {
+        let mut count = 0;
 
         while let Some(&b) = source.get(self.index()) {
-            if b.is_ascii_digit() && !zero {
-                self.next()
-            } else if b == b'.' && !point {
-                point = true;
-                zero = false;
-
+            if (b as char).is_digit(radix) {
+                count += 1;
                 self.next();
+            } else if b == b'_' {
+                if count == 0 {
+                    return Err(Error::at(
+                        ErrorKind::InvalidNumber,
+                        "digit separator must come after a digit",
+                        self.here(),
+                    ));
+                }
+
+                match source.get(self.index() + 1) {
+                    Some(&b) if (b as char).is_digit(radix) => self.next(),
+                    Some(b'_') => {
+                        self.next();
 
-                if let Some(b) = source.get(self.index()) {
-                    if !b.is_ascii_digit() {
-                        return Err(Error {
-                            desc: format!(
-                                "decimal point must be followed with a digit, not '{}' ({}:{})",
-                                *b as char,
-                                self.line(),
-                                self.column(),
-                            ),
-                        });
+                        return Err(Error::at(
+                            ErrorKind::InvalidNumber,
+                            "digit separators cannot follow each other",
+                            self.here(),
+                        ));
+                    }
+                    _ => {
+                        return Err(Error::at(
+                            ErrorKind::InvalidNumber,
+                            "digit separator must be followed with a digit",
+                            self.here(),
+                        ))
                     }
-                } else {
-                    return Err(Error {
-                        desc: "decimal point must be followed with a digit, but no bytes left"
-                            .to_string(),
-                    });
                 }
             } else {
                 break;
             }
         }
 
-        let end = self.position();
-        let raw = &source[start.index()..end.index()];
+        Ok(count)
+    }
 
-        Ok(TokenKind::Literal(LiteralKind::Number(Token::new(
-            Location::new(start, end),
-            raw,
-        ))))
+    /// Digits of a `0x`, `0o` or `0b` number, up to the first byte that
+    /// cannot continue it.
+    fn radix_digits(&mut self, source: &[u8], radix: u32, name: &str) -> Result<()> {
+        let count = self.digits(source, radix)?;
+
+        if let Some(&b) = source.get(self.index()) {
+            if b.is_ascii_alphanumeric() {
+                return Err(Error::at(
+                    ErrorKind::InvalidNumber,
+                    format!("invalid digit '{}' in {} number", b as char, name),
+                    self.here(),
+                ));
+            }
+        }
+
+        if count == 0 {
+            return Err(Error::at(
+                ErrorKind::InvalidNumber,
+                format!("{} number has no digits", name),
+                self.here(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Digits of a decimal number, with an optional fraction and exponent.
+    fn decimal(&mut self, source: &[u8], start: Position) -> Result<()> {
+        match source.get(self.index()) {
+            // a leading zero stands alone
+            Some(b'0') => {
+                self.next();
+
+                if let Some(&b) = source
+                    .get(self.index())
+                    .filter(|b| b.is_ascii_digit() || **b == b'_')
+                {
+                    return Err(Error::at(
+                        ErrorKind::InvalidNumber,
+                        format!("leading zero cannot be followed with '{}'", b as char),
+                        self.here(),
+                    ));
+                }
+            }
+            Some(b) if b.is_ascii_digit() => {
+                self.digits(source, 10)?;
+            }
+            Some(b) => {
+                return Err(Error::at(
+                    ErrorKind::InvalidNumber,
+                    format!("sign must be followed with a digit, not '{}'", *b as char),
+                    self.here(),
+                ))
+            }
+            None => {
+                return Err(Error::at(
+                    ErrorKind::InvalidNumber,
+                    "sign must be followed with a digit, but no bytes left",
+                    self.location(start),
+                ))
+            }
+        }
+
+        if source.get(self.index()) == Some(&b'.') {
+            self.next(); // skip decimal point
+
+            match source.get(self.index()) {
+                Some(b) if b.is_ascii_digit() => {
+                    self.digits(source, 10)?;
+                }
+                Some(b) => {
+                    return Err(Error::at(
+                        ErrorKind::InvalidNumber,
+                        format!(
+                            "decimal point must be followed with a digit, not '{}'",
+                            *b as char,
+                        ),
+                        self.here(),
+                    ))
+                }
+                None => {
+                    return Err(Error::at(
+                        ErrorKind::InvalidNumber,
+                        "decimal point must be followed with a digit, but no bytes left",
+                        self.location(start),
+                    ))
+                }
+            }
+        }
+
+        if let Some(b'e' | b'E') = source.get(self.index()) {
+            self.next(); // skip exponent marker
+
+            if let Some(b'+' | b'-') = source.get(self.index()) {
+                self.next();
+            }
+
+            return self.exponent(source, start);
+        }
+
+        Ok(())
+    }
+
+    fn exponent(&mut self, source: &[u8], start: Position) -> Result<()> {
+        if self.digits(source, 10)? == 0 {
+            let desc = match source.get(self.index()) {
+                Some(b) => format!(
+                    "exponent must be followed with a digit, not '{}'",
+                    *b as char
+                ),
+                None => "exponent must be followed with a digit, but no bytes left".to_string(),
+            };
+
+            let loc = match source.get(self.index()) {
+                Some(_) => self.here(),
+                None => self.location(start),
+            };
+
+            return Err(Error::at(ErrorKind::InvalidNumber, desc, loc));
+        }
+
+        Ok(())
     }
 
     fn template_string<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
+        let start = self.position();
+
         self.next(); // skip opening tilde
 
-        let start = self.position();
+        let content = self.index();
+        let mut invalid = None;
 
         while let Some(&b) = source.get(self.index()) {
             match b {
                 b'\\' => {
-                    // escape char
-                    self.next();
-                    self.next();
+                    let error = self.escape(source);
+                    invalid = invalid.or(error);
                 }
                 b'\n' => {
                     self.next_line();
@@ -184,55 +753,74 @@ impl Lexer {
         }
 
         if source.get(self.index()).is_none() {
-            return Err(Error {
-                desc: format!(
-                    "unterminated template string ({}:{})",
-                    start.line(),
-                    start.column()
-                ),
-            });
+            return Err(Error::at(
+                ErrorKind::UnterminatedString,
+                "unterminated template string",
+                self.location(start),
+            ));
         }
 
-        let end = self.position();
+        let raw = &source[content..self.index()];
 
         self.next(); // skip closing tilde
 
-        let raw = &source[start.index()..end.index()];
+        if let Some(e) = invalid {
+            return Err(e);
+        }
 
-        Ok(TokenKind::Literal(LiteralKind::String(Token::new(
-            Location::new(start, end),
+        Ok(TokenKind::Literal(LiteralKind::TemplateString(Token::new(
+            self.location(start),
             raw,
         ))))
     }
 
-    fn ignore_comment(&mut self, source: &[u8]) -> Result<()> {
+    fn whitespace<'a>(&mut self, source: &'a [u8]) -> TokenKind<'a> {
         let start = self.position();
 
-        self.next(); // skip identifier forward slash
-
-        loop {
-            if let Some(&b) = source.get(self.index()) {
-                if b == b'\n' {
-                    break;
-                } else {
-                    self.next();
-                }
-            } else {
-                return Err(Error {
-                    desc: format!("unterminated comment ({}:{})", start.line(), start.column()),
-                });
-            }
+        while let Some(b'\r' | b'\t' | b' ') = source.get(self.index()) {
+            self.next();
         }
 
+        TokenKind::Trivia(TriviaKind::Whitespace(Token::new(
+            self.location(start),
+            &source[start.index()..self.index()],
+        )))
+    }
+
+    fn newline<'a>(&mut self, source: &'a [u8]) -> TokenKind<'a> {
+        let start = self.position();
+
         self.next_line();
 
-        Ok(())
+        TokenKind::Trivia(TriviaKind::Newline(Token::new(
+            Location::new(
+                start,
+                Position::new(start.line(), start.column() + 1, self.index()),
+            ),
+            &source[start.index()..self.index()],
+        )))
     }
 
-    fn ignore_multiline_comment(&mut self, source: &[u8]) -> Result<()> {
-        let start = self.position();
+    fn line_comment<'a>(&mut self, source: &'a [u8], start: Position) -> TokenKind<'a> {
+        self.next(); // skip second forward slash
 
-        self.next(); // skip preceding opening slash
+        // the newline is left for the next token, a comment may also end the input
+        while let Some(&b) = source.get(self.index()) {
+            if b == b'\n' {
+                break;
+            }
+
+            self.next();
+        }
+
+        TokenKind::Trivia(TriviaKind::LineComment(Token::new(
+            self.location(start),
+            &source[start.index()..self.index()],
+        )))
+    }
+
+    fn block_comment<'a>(&mut self, source: &'a [u8], start: Position) -> Result<TokenKind<'a>> {
+        self.next(); // skip opening asterisk
 
         loop {
             if let Some(&b) = source.get(self.index()) {
@@ -249,46 +837,41 @@ impl Lexer {
                     self.next();
                 }
             } else {
-                return Err(Error {
-                    desc: format!("unterminated comment ({}:{})", start.line(), start.column()),
-                });
+                return Err(Error::at(
+                    ErrorKind::UnterminatedComment,
+                    "unterminated comment",
+                    self.location(start),
+                ));
             }
         }
 
-        Ok(())
+        Ok(TokenKind::Trivia(TriviaKind::BlockComment(Token::new(
+            self.location(start),
+            &source[start.index()..self.index()],
+        ))))
     }
 
-    fn comment(&mut self, source: &[u8]) -> Result<()> {
+    fn comment<'a>(&mut self, source: &'a [u8]) -> Result<TokenKind<'a>> {
         let start = self.position(); // save start position
 
         self.next(); // skip preceding opening slash
 
         match source.get(self.index()) {
-            Some(b'/') => match self.ignore_comment(source) {
-                Ok(()) => Ok(()),
-                Err(e) => Err(e),
-            },
+            Some(b'/') => Ok(self.line_comment(source, start)),
 
-            Some(b'*') => match self.ignore_multiline_comment(source) {
-                Ok(()) => Ok(()),
-                Err(e) => Err(e),
-            },
-            Some(c) => Err(Error {
-                desc: format!(
-                    "expected '/' or '*' not '{}' ({}:{})",
-                    *c as char,
-                    self.line(),
-                    self.column()
-                ),
-            }),
+            Some(b'*') => self.block_comment(source, start),
 
-            None => Err(Error {
-                desc: format!(
-                    "expected '/' or '*' but no bytes left ({}:{})",
-                    start.line(),
-                    start.column()
-                ),
-            }),
+            Some(c) => Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                format!("expected '/' or '*' not '{}'", *c as char),
+                self.here(),
+            )),
+
+            None => Err(Error::at(
+                ErrorKind::UnexpectedEof,
+                "expected '/' or '*' but no bytes left",
+                self.location(start),
+            )),
         }
     }
 
@@ -297,77 +880,188 @@ impl Lexer {
             index: 0,
             column: 1,
             line: 1,
+            strict: false,
+        }
+    }
+
+    /// Rejects unknown escapes such as `\q`, and broken ones such as
+    /// `\u{110000}`, instead of keeping the escaped character as it is.
+    pub fn with_strict_escapes(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Lexes the token or trivia starting at the cursor.
+    fn token<'a>(&mut self, source: &'a [u8], b: u8) -> Result<TokenKind<'a>> {
+        let token = self.lex(source, b)?;
+
+        // only strings and comments can hold bytes outside of ASCII
+        if let TokenKind::Literal(LiteralKind::String(_) | LiteralKind::TemplateString(_))
+        | TokenKind::Trivia(TriviaKind::LineComment(_) | TriviaKind::BlockComment(_)) = token
+        {
+            let loc = token.loc();
+            check_utf8(
+                &source[loc.start().index()..loc.end().index()],
+                *loc.start(),
+            )?;
+        }
+
+        Ok(token)
+    }
+
+    fn lex<'a>(&mut self, source: &'a [u8], b: u8) -> Result<TokenKind<'a>> {
+        match b {
+            b'{' => Ok(TokenKind::Delimiter(DelimiterKind::TablePrec(
+                self.delimiter(source),
+            ))),
+            b'}' => Ok(TokenKind::Delimiter(DelimiterKind::TableTerm(
+                self.delimiter(source),
+            ))),
+            b'[' => Ok(TokenKind::Delimiter(DelimiterKind::ListPrec(
+                self.delimiter(source),
+            ))),
+            b']' => Ok(TokenKind::Delimiter(DelimiterKind::ListTerm(
+                self.delimiter(source),
+            ))),
+            b'\n' => Ok(self.newline(source)),
+
+            // whitespaces
+            b'\r' | b'\t' | b' ' => Ok(self.whitespace(source)),
+
+            // String
+            b'"' => self.string(source),
+
+            // Template String
+            b'`' => self.template_string(source),
+
+            // Number
+            b'0'..=b'9' | b'+' | b'-' => self.number(source),
+
+            // Comments
+            b'/' => self.comment(source),
+
+            // Directive
+            b'@' => self.directive(source),
+
+            // References
+            b'&' | b'*' => self.anchor(source),
+            b'$' => self.reference(source),
+
+            // identifier
+            _ if char_at(source, self.index()).is_some_and(is_identifier_start) => {
+                self.identifier(source)
+            }
+
+            _ if source[self.index()..].starts_with(BOM) => Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                "unexpected byte order mark, strip it with mcl::strip_bom",
+                self.location_at(BOM.len()),
+            )),
+
+            b if !b.is_ascii() => match char_at(source, self.index()) {
+                Some(c) => Err(Error::at(
+                    ErrorKind::UnexpectedCharacter,
+                    format!("unrecognized character '{}'", c),
+                    self.location_at(c.len_utf8()),
+                )),
+                None => {
+                    let rest = &source[self.index()..];
+                    let len = rest.len().min(4);
+
+                    Err(check_utf8(&rest[..len], self.position()).unwrap_err())
+                }
+            },
+
+            _ => Err(Error::at(
+                ErrorKind::UnexpectedCharacter,
+                format!("unrecognized character '{}'", b as char),
+                self.here(),
+            )),
         }
     }
 
     pub fn tokenize<'a>(&mut self, source: &'a [u8]) -> Result<Vec<TokenKind<'a>>> {
         let mut tokens = Vec::with_capacity(source.len());
 
-        while let Some(b) = source.get(self.index()) {
-            match b {
-                b'{' => {
-                    tokens.push(TokenKind::Delimiter(DelimiterKind::TablePrec));
-                    self.next();
-                }
-                b'}' => {
-                    tokens.push(TokenKind::Delimiter(DelimiterKind::TableTerm));
-                    self.next();
-                }
-                b'[' => {
-                    tokens.push(TokenKind::Delimiter(DelimiterKind::ListPrec));
-                    self.next();
-                }
-                b']' => {
-                    tokens.push(TokenKind::Delimiter(DelimiterKind::ListTerm));
-                    self.next();
-                }
-                b'\n' => self.next_line(),
-
-                // skip whitespaces
-                b'\r' | b'\t' | b' ' => self.next(),
-
-                // identifier
-                b'a'..=b'z' | b'A'..=b'Z' => match self.identifier(source) {
-                    Ok(t) => tokens.push(t),
-                    Err(e) => return Err(e),
-                },
-
-                // String
-                b'"' => match self.string(source) {
-                    Ok(t) => tokens.push(t),
-                    Err(e) => return Err(e),
-                },
-
-                // Template String
-                b'`' => match self.template_string(source) {
-                    Ok(t) => tokens.push(t),
-                    Err(e) => return Err(e),
-                },
-
-                // Number
-                b'0'..=b'9' | b'+' | b'-' => match self.number(source) {
-                    Ok(t) => tokens.push(t),
-                    Err(e) => return Err(e),
-                },
-
-                // Comments
-                b'/' => match self.comment(source) {
-                    Ok(()) => {}
-                    Err(e) => return Err(e),
-                },
-                _ => {
-                    return Err(Error {
-                        desc: format!(
-                            "unrecognized character '{}' ({}:{})",
-                            *b as char,
-                            self.line(),
-                            self.column(),
-                        ),
-                    })
-                }
+        while let Some(&b) = source.get(self.index()) {
+            let token = self.token(source, b)?;
+
+            if !token.is_trivia() {
+                tokens.push(token);
             }
         }
 
         Ok(tokens)
     }
+
+    /// Like [`Lexer::tokenize`], but keeps whitespace, newlines and comments
+    /// as [`TriviaKind`] tokens, so that concatenating the source of every
+    /// token gives back the input.
+    pub fn tokenize_with_trivia<'a>(&mut self, source: &'a [u8]) -> Result<Vec<TokenKind<'a>>> {
+        let mut tokens = Vec::with_capacity(source.len());
+
+        while let Some(&b) = source.get(self.index()) {
+            tokens.push(self.token(source, b)?);
+        }
+
+        Ok(tokens)
+    }
+
+    /// Like [`Lexer::tokenize`], but keeps going after an error by skipping
+    /// past the offending bytes.
+    pub fn tokenize_recovering<'a>(
+        &mut self,
+        source: &'a [u8],
+    ) -> (Vec<TokenKind<'a>>, Vec<Error>) {
+        let mut tokens = Vec::with_capacity(source.len());
+        let mut errors = Vec::new();
+
+        while let Some(&b) = source.get(self.index()) {
+            let start = self.position();
+
+            match self.token(source, b) {
+                Ok(token) if token.is_trivia() => {}
+                Ok(token) => tokens.push(token),
+                Err(e) => {
+                    // keep the broken string so the parser does not trip on a
+                    // key without value
+                    if matches!(b, b'"' | b'`')
+                        && matches!(
+                            e.kind(),
+                            ErrorKind::NewlineInString
+                                | ErrorKind::UnterminatedString
+                                | ErrorKind::InvalidEscape
+                                | ErrorKind::InvalidUtf8
+                        )
+                    {
+                        // a string with a broken escape or invalid bytes did
+                        // get its closing quote
+                        let end = match e.kind() {
+                            ErrorKind::InvalidEscape | ErrorKind::InvalidUtf8 => self.index() - 1,
+                            _ => self.index(),
+                        };
+
+                        let raw = &source[start.index() + 1..end];
+                        let token = Token::new(self.location(start), raw);
+
+                        // placeholders are only read out of valid UTF-8
+                        tokens.push(TokenKind::Literal(match (b, e.kind()) {
+                            (b'`', kind) if kind != ErrorKind::InvalidUtf8 => {
+                                LiteralKind::TemplateString(token)
+                            }
+                            _ => LiteralKind::String(token),
+                        }));
+                    }
+
+                    errors.push(e);
+
+                    if self.index() == start.index() {
+                        self.next();
+                    }
+                }
+            }
+        }
+
+        (tokens, errors)
+    }
 }
@@ -1,32 +1,85 @@
 pub use crate::prelude::*;
 
+pub use regex;
 pub use serde_json;
 
+pub mod config;
+pub mod cst;
+pub mod de;
+pub mod diagnostic;
+pub mod env;
 pub mod error;
+pub mod fmt;
+pub mod include;
 pub mod lexer;
+pub mod loader;
+pub mod merge;
 pub mod parser;
 pub mod prelude;
+pub mod reference;
+pub mod schema;
+pub mod ser;
+pub mod template;
 pub mod token;
+pub mod value;
+
+pub use de::from_file;
+pub use de::from_slice;
+pub use de::from_str;
+pub use de::from_value;
+pub use de::Deserializer;
+pub use fmt::format;
+pub use fmt::FormatOptions;
+pub use lexer::strip_bom;
+pub use ser::to_string;
+pub use ser::to_string_pretty;
+pub use ser::to_writer;
+pub use ser::to_writer_pretty;
+pub use ser::Serializer;
+
+pub use value::Value;
 
 use lexer::Lexer;
 use parser::Parser;
 
-pub fn from_str(v: &str) -> Result<serde_json::Value> {
+/// Parses MCL bytes into a [`Value`] that keeps key order and locations.
+pub fn parse(v: &[u8]) -> Result<Value> {
     let mut lexer = Lexer::new();
-    let tokens = lexer.tokenize(v.as_bytes())?;
+    let tokens = lexer.tokenize(v)?;
 
     let mut parser = Parser::new();
-    let output = parser.parse(&tokens)?;
+    parser.parse(&tokens)
+}
 
-    Ok(output)
+/// Like [`parse`], with `variables` filling the `${name}` placeholders that
+/// do not name a value of the document.
+pub fn parse_with_variables(v: &[u8], variables: value::Table) -> Result<Value> {
+    Parser::new().with_variables(variables).parse_slice(v)
 }
 
-pub fn from_slice(v: &[u8]) -> Result<serde_json::Value> {
+impl std::str::FromStr for Value {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Value> {
+        parse(s.as_bytes())
+    }
+}
+
+/// Parses `v` while collecting every lexer and parser error instead of
+/// stopping at the first one, returning whatever could be parsed.
+pub fn from_slice_recovering(v: &[u8]) -> (Value, Vec<Error>) {
     let mut lexer = Lexer::new();
-    let tokens = lexer.tokenize(v)?;
+    let (tokens, mut errors) = lexer.tokenize_recovering(v);
 
     let mut parser = Parser::new();
-    let output = parser.parse(&tokens)?;
+    let (output, parse_errors) = parser.parse_recovering(&tokens);
+
+    errors.extend(parse_errors);
+    errors.sort_by_key(|e| e.loc().map(|l| l.start().index()));
+
+    (output, errors)
+}
 
-    Ok(output)
+pub fn from_str_recovering(v: &str) -> (Value, Vec<Error>) {
+    from_slice_recovering(v.as_bytes())
 }
@@ -0,0 +1,281 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+
+use crate::prelude::*;
+
+use crate::include::FileSystem;
+use crate::include::OsFileSystem;
+use crate::merge::ListMerge;
+use crate::merge::Merger;
+use crate::merge::Origins;
+use crate::parser::Parser;
+use crate::value::Number;
+use crate::value::Table;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+enum Source {
+    File { path: PathBuf, required: bool },
+    Text { name: String, text: Vec<u8> },
+    Values { name: String, table: Table },
+}
+
+/// Loads a config out of several layers, such as a base file, an
+/// environment specific file, local overrides and environment variables.
+///
+/// Layers are merged in the order they were added, so a later layer takes
+/// precedence over an earlier one:
+///
+/// - tables are merged key by key, keys keeping the position where they
+///   first appeared
+/// - lists replace the earlier list, or are appended to it with
+///   [`ListMerge::Append`]
+/// - any other value replaces the earlier value, whatever its type
+///
+/// ```
+/// use mcl::loader::ConfigLoader;
+///
+/// let config = ConfigLoader::new()
+///     .string("base", "server { host \"0.0.0.0\" port 80 }")
+///     .string("local", "server { port 8080 }")
+///     .load()
+///     .unwrap();
+///
+/// assert_eq!(config.value().pointer("server.port").unwrap().as_u64(), Some(8080));
+/// assert_eq!(config.origin("server.host"), Some("base"));
+/// ```
+#[derive(Default)]
+pub struct ConfigLoader {
+    sources: Vec<Source>,
+    lists: ListMerge,
+    fs: Option<Rc<dyn FileSystem>>,
+}
+
+impl std::fmt::Debug for ConfigLoader {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("ConfigLoader")
+            .field("sources", &self.sources.len())
+            .field("lists", &self.lists)
+            .field("fs", &self.fs.is_some())
+            .finish()
+    }
+}
+
+impl ConfigLoader {
+    pub fn new() -> ConfigLoader {
+        ConfigLoader::default()
+    }
+
+    /// Adds a layer read from the file at `path`, which must exist.
+    pub fn file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File {
+            path: path.as_ref().to_path_buf(),
+            required: true,
+        });
+        self
+    }
+
+    /// Adds a layer read from the file at `path`, skipped if there is no
+    /// such file.
+    pub fn optional_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.sources.push(Source::File {
+            path: path.as_ref().to_path_buf(),
+            required: false,
+        });
+        self
+    }
+
+    /// Adds a layer parsed from MCL `text`, reported as `name`.
+    pub fn string(mut self, name: impl Into<String>, text: impl AsRef<[u8]>) -> Self {
+        self.sources.push(Source::Text {
+            name: name.into(),
+            text: text.as_ref().to_vec(),
+        });
+        self
+    }
+
+    /// Adds an already built layer, reported as `name`.
+    pub fn table(mut self, name: impl Into<String>, table: Table) -> Self {
+        self.sources.push(Source::Values {
+            name: name.into(),
+            table,
+        });
+        self
+    }
+
+    /// Adds a layer out of the variables of `vars` starting with `prefix`,
+    /// such as `std::env::vars()`, reported as `env`.
+    ///
+    /// The prefix is dropped, the rest lowercased and split on `__` into
+    /// nested keys, so `APP_SERVER__PORT=8080` sets `server.port` to the
+    /// number `8080`. Values reading as booleans or numbers become those,
+    /// every other value stays a string.
+    pub fn env<I, K, V>(self, prefix: &str, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter_map(|(k, v)| {
+                let key = k.as_ref().strip_prefix(prefix)?.to_lowercase();
+                (!key.is_empty()).then(|| (key, v.as_ref().to_string()))
+            })
+            .collect();
+
+        // the environment comes in no particular order
+        vars.sort();
+
+        let mut table = Table::new();
+
+        for (key, value) in vars {
+            let path: Vec<&str> = key.split("__").collect();
+            insert(&mut table, &path, env_value(&value));
+        }
+
+        self.table("env", table)
+    }
+
+    /// How a list combines with the list of an earlier layer, replacing it
+    /// by default.
+    pub fn with_list_merge(mut self, lists: ListMerge) -> Self {
+        self.lists = lists;
+        self
+    }
+
+    /// Reads files, and the files they include, through `fs`.
+    pub fn with_fs(mut self, fs: impl FileSystem + 'static) -> Self {
+        self.fs = Some(Rc::new(fs));
+        self
+    }
+
+    /// Parses and merges every layer, failing on the first broken one.
+    pub fn load(&self) -> Result<Config> {
+        let fs: Rc<dyn FileSystem> = self.fs.clone().unwrap_or_else(|| Rc::new(OsFileSystem));
+
+        let mut merger = Merger::new(self.lists);
+        let mut value = Value::new(ValueKind::Table(Table::new()));
+
+        for source in &self.sources {
+            let (name, layer) = match source {
+                Source::File { path, required } => {
+                    let name = path.display().to_string();
+
+                    let text = match fs.read(path) {
+                        Ok(text) => text,
+                        Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => continue,
+                        Err(e) => {
+                            return Err(Error::new(
+                                ErrorKind::Io,
+                                format!("cannot read '{}': {}", name, e),
+                            ))
+                        }
+                    };
+
+                    let layer = Parser::new()
+                        .with_fs(fs.clone())
+                        .with_file(path)
+                        .parse_slice(crate::strip_bom(&text))?;
+
+                    (name, layer)
+                }
+
+                Source::Text { name, text } => {
+                    let layer = Parser::new()
+                        .parse_slice(text)
+                        .map_err(|e| e.in_file(Some(name)))?;
+
+                    (name.clone(), layer)
+                }
+
+                Source::Values { name, table } => {
+                    (name.clone(), Value::new(ValueKind::Table(table.clone())))
+                }
+            };
+
+            if layer.as_table().is_none() {
+                return Err(Error::new(
+                    ErrorKind::InvalidType,
+                    format!(
+                        "layer '{}' must be a table, not a {}",
+                        name,
+                        layer.type_name()
+                    ),
+                ));
+            }
+
+            merger.merge(&mut value, layer, "", &name);
+        }
+
+        Ok(Config {
+            value,
+            origins: merger.into_origins(),
+        })
+    }
+}
+
+fn insert(table: &mut Table, path: &[&str], value: Value) {
+    match path {
+        [] => {}
+        [key] => {
+            table.insert(*key, value);
+        }
+        [key, rest @ ..] => {
+            if table.get(key).and_then(Value::as_table).is_none() {
+                table.insert(*key, Value::new(ValueKind::Table(Table::new())));
+            }
+
+            if let Some(inner) = table.get_mut(key).and_then(Value::as_table_mut) {
+                insert(inner, rest, value);
+            }
+        }
+    }
+}
+
+fn env_value(text: &str) -> Value {
+    match text {
+        "true" => Value::from(true),
+        "false" => Value::from(false),
+        text if text.parse::<i64>().is_ok() || text.parse::<f64>().is_ok_and(f64::is_finite) => {
+            Value::new(ValueKind::Number(Number::new(text)))
+        }
+        text => Value::from(text),
+    }
+}
+
+/// The merged value of every layer of a [`ConfigLoader`].
+#[derive(Debug, Clone)]
+pub struct Config {
+    value: Value,
+    origins: Origins,
+}
+
+impl Config {
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /// Name of the layer that supplied the value at `path`: the path of a
+    /// file, or the name given to a string or table layer.
+    ///
+    /// Only scalars, and empty tables and lists, have a single origin.
+    pub fn origin(&self, path: &str) -> Option<&str> {
+        self.origins.get(path)
+    }
+
+    pub fn origins(&self) -> &Origins {
+        &self.origins
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        crate::from_value(&self.value)
+    }
+}
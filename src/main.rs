@@ -0,0 +1,225 @@
+use std::io::Read;
+use std::io::Write;
+use std::process::ExitCode;
+
+use mcl::include::resolve;
+use mcl::include::OsFileSystem;
+use mcl::lexer::Lexer;
+use mcl::parser::Parser;
+use mcl::Error;
+use mcl::Value;
+
+const USAGE: &str = "\
+usage: mcl <command> [args]
+
+commands:
+    to-json [file]          convert MCL to JSON
+    from-json [file]        convert JSON to MCL
+    check [file]            report every error in an MCL file
+    get [file] <path>       print the value at a dotted path, e.g. server.port
+    fmt [--align] [file]    print the file in the canonical style
+
+input is read from stdin when no file is given";
+
+/// Input bytes along with the name used in diagnostics.
+struct Input {
+    name: String,
+    bytes: Vec<u8>,
+    file: bool,
+}
+
+fn read(file: Option<&str>) -> Result<Input, String> {
+    match file {
+        Some(path) => match std::fs::read(path) {
+            Ok(bytes) => Ok(Input {
+                name: path.to_string(),
+                bytes: mcl::strip_bom(&bytes).to_vec(),
+                file: true,
+            }),
+            Err(e) => Err(format!("cannot read '{}': {}", path, e)),
+        },
+        None => {
+            let mut bytes = Vec::new();
+
+            match std::io::stdin().read_to_end(&mut bytes) {
+                Ok(_) => Ok(Input {
+                    name: "<stdin>".to_string(),
+                    bytes: mcl::strip_bom(&bytes).to_vec(),
+                    file: false,
+                }),
+                Err(e) => Err(format!("cannot read stdin: {}", e)),
+            }
+        }
+    }
+}
+
+/// Parser resolving includes from the disk, relative to the input file.
+fn parser(input: &Input) -> Parser {
+    let parser = Parser::new().with_fs(OsFileSystem);
+
+    match input.file {
+        true => parser.with_file(&input.name),
+        false => parser,
+    }
+}
+
+/// Renders `error`, reading the included file it points into if needed.
+fn report(input: &Input, error: &Error) -> String {
+    match error.file() {
+        Some(file) if file != input.name => {
+            let source = std::fs::read(file).unwrap_or_default();
+
+            mcl::diagnostic::render(file, mcl::strip_bom(&source), error)
+        }
+        _ => mcl::diagnostic::render(&input.name, &input.bytes, error),
+    }
+}
+
+fn parse(input: &Input) -> Result<Value, String> {
+    parser(input)
+        .parse_slice(&input.bytes)
+        .map_err(|e| report(input, &e))
+}
+
+fn to_json(file: Option<&str>) -> Result<String, String> {
+    let input = read(file)?;
+    let value = parse(&input)?;
+
+    serde_json::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn from_json(file: Option<&str>) -> Result<String, String> {
+    let input = read(file)?;
+
+    let value: Value = serde_json::from_slice(&input.bytes)
+        .map_err(|e| format!("error: {}\n --> {}", e, input.name))?;
+
+    mcl::to_string_pretty(&value).map_err(|e| e.to_string())
+}
+
+fn check(file: Option<&str>) -> Result<String, String> {
+    let input = read(file)?;
+
+    let (tokens, mut errors) = Lexer::new().tokenize_recovering(&input.bytes);
+    let (_, parse_errors) = parser(&input).parse_recovering(&tokens);
+
+    errors.extend(parse_errors);
+
+    // errors of the input first, then those of the files it includes
+    let main = input.file.then(|| resolve(None, &input.name));
+    let included = |e: &Error| e.file().is_some_and(|f| Some(resolve(None, f)) != main);
+
+    errors.sort_by_key(|e| {
+        (
+            included(e),
+            e.file.clone(),
+            e.loc().map(|l| l.start().index()),
+        )
+    });
+
+    if errors.is_empty() {
+        return Ok(String::new());
+    }
+
+    let mut text = String::new();
+
+    for error in &errors {
+        text.push_str(&report(&input, error));
+        text.push('\n');
+    }
+
+    text.push_str(&format!(
+        "{}: {} error{} found",
+        input.name,
+        errors.len(),
+        if errors.len() == 1 { "" } else { "s" }
+    ));
+
+    Err(text)
+}
+
+fn get(file: Option<&str>, path: &str) -> Result<String, String> {
+    let input = read(file)?;
+    let value = parse(&input)?;
+
+    let found = value
+        .pointer(path)
+        .ok_or_else(|| format!("error: '{}' not found in {}", path, input.name))?;
+
+    if let Some(s) = found.as_str() {
+        return Ok(s.to_string());
+    }
+
+    if found.as_table().is_some() || found.as_list().is_some() {
+        return mcl::to_string_pretty(found).map_err(|e| e.to_string());
+    }
+
+    // a scalar on its own is not a document
+    let mut serializer = mcl::Serializer::new(Vec::new()).braced();
+
+    serde::Serialize::serialize(found, &mut serializer).map_err(|e| e.to_string())?;
+
+    String::from_utf8(serializer.into_inner()).map_err(|e| e.to_string())
+}
+
+fn fmt(file: Option<&str>, align: bool) -> Result<String, String> {
+    let input = read(file)?;
+
+    let options = mcl::FormatOptions {
+        align_values: align,
+        ..Default::default()
+    };
+
+    mcl::format(&input.bytes, &options)
+        .map(|text| text.trim_end().to_string())
+        .map_err(|e| mcl::diagnostic::render(&input.name, &input.bytes, &e))
+}
+
+fn run(args: &[String]) -> Option<Result<String, String>> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let result = match args.as_slice() {
+        ["to-json"] => to_json(None),
+        ["to-json", file] => to_json(Some(file)),
+        ["from-json"] => from_json(None),
+        ["from-json", file] => from_json(Some(file)),
+        ["check"] => check(None),
+        ["check", file] => check(Some(file)),
+        ["get", path] => get(None, path),
+        ["get", file, path] => get(Some(file), path),
+        ["fmt"] => fmt(None, false),
+        ["fmt", "--align"] => fmt(None, true),
+        ["fmt", "--align", file] | ["fmt", file, "--align"] => fmt(Some(file), true),
+        ["fmt", file] => fmt(Some(file), false),
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if let Some("help" | "-h" | "--help") = args.first().map(String::as_str) {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match run(&args) {
+        Some(Ok(output)) => {
+            if !output.is_empty() {
+                let _ = writeln!(std::io::stdout(), "{}", output);
+            }
+
+            ExitCode::SUCCESS
+        }
+        Some(Err(report)) => {
+            eprintln!("{}", report);
+            ExitCode::FAILURE
+        }
+        None => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeMap;
+
+use crate::value::Table;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+/// What happens to a list that is merged over another list.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ListMerge {
+    /// The new list takes the place of the old one.
+    #[default]
+    Replace,
+    /// The items of the new list go after those of the old one.
+    Append,
+}
+
+/// What happens to a key written twice in the same table.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// The second key is an error pointing at both keys.
+    #[default]
+    Error,
+    /// The later value replaces the earlier one, the key keeping its place.
+    LastWins,
+    /// The later value is ignored.
+    FirstWins,
+    /// Two tables are merged key by key, following the same policy all the
+    /// way down, any other later value replacing the earlier one.
+    MergeTables,
+}
+
+/// Source of every value of a merged document, keyed by dot separated path.
+///
+/// Only scalars, and empty tables and lists, are recorded, as the values of
+/// a table or list may come from different sources.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Origins {
+    map: BTreeMap<String, String>,
+}
+
+impl Origins {
+    pub fn new() -> Origins {
+        Origins::default()
+    }
+
+    /// Source the value at `path` came from.
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.map.get(path).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.map.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Records `source` for every value within `value`, found at `path`.
+    fn record(&mut self, path: &str, value: &Value, source: &str) {
+        match value.kind() {
+            ValueKind::Table(t) if !t.is_empty() => {
+                for (key, value) in t.iter() {
+                    self.record(&join(path, key), value, source);
+                }
+            }
+            ValueKind::List(l) if !l.is_empty() => {
+                for (i, value) in l.iter().enumerate() {
+                    self.record(&join(path, &i.to_string()), value, source);
+                }
+            }
+            _ => {
+                self.map.insert(path.to_string(), source.to_string());
+            }
+        }
+    }
+
+    /// Forgets the sources of `path` and everything within it.
+    fn forget(&mut self, path: &str) {
+        self.map.retain(|k, _| !within(k, path));
+    }
+
+    /// Sources of the values within `path`.
+    pub(crate) fn within(&self, path: &str) -> Vec<(String, String)> {
+        self.map
+            .iter()
+            .filter(|(k, _)| within(k, path))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
+    pub(crate) fn restore(&mut self, sources: Vec<(String, String)>) {
+        self.map.extend(sources);
+    }
+
+    /// Gives the values within `to` the sources known for the same values
+    /// within `from`.
+    pub(crate) fn copy(&mut self, from: &str, to: &str) {
+        let copies: Vec<(String, String)> = self
+            .map
+            .iter()
+            .filter(|(k, _)| within(k, from))
+            .map(|(k, v)| (rebase(k, from, to), v.clone()))
+            .collect();
+
+        self.map.extend(copies);
+    }
+}
+
+/// Whether `path` is `prefix` or lies inside of it.
+fn within(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Rewrites `path`, which lies within `from`, to the same place within `to`.
+fn rebase(path: &str, from: &str, to: &str) -> String {
+    format!("{}{}", to, &path[from.len()..])
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Deep merges values, remembering which source every value came from.
+///
+/// Tables are merged key by key, keeping the order in which keys first
+/// appeared, lists follow the [`ListMerge`] rule and any other value is
+/// replaced.
+#[derive(Debug, Clone, Default)]
+pub struct Merger {
+    lists: ListMerge,
+    origins: Origins,
+}
+
+impl Merger {
+    pub fn new(lists: ListMerge) -> Merger {
+        Merger {
+            lists,
+            origins: Origins::new(),
+        }
+    }
+
+    pub fn lists(&self) -> ListMerge {
+        self.lists
+    }
+
+    pub fn origins(&self) -> &Origins {
+        &self.origins
+    }
+
+    pub(crate) fn origins_mut(&mut self) -> &mut Origins {
+        &mut self.origins
+    }
+
+    pub fn into_origins(self) -> Origins {
+        self.origins
+    }
+
+    /// Merges `over`, which came from `source`, into `base`, the value at
+    /// `path` of the document.
+    pub fn merge(&mut self, base: &mut Value, over: Value, path: &str, source: &str) {
+        let append = self.lists == ListMerge::Append;
+
+        match (base.kind_mut(), over.kind()) {
+            (ValueKind::Table(_), ValueKind::Table(_)) => {
+                if let (Some(table), ValueKind::Table(over)) =
+                    (base.as_table_mut(), over.into_kind())
+                {
+                    self.merge_table(table, over, path, source);
+                }
+            }
+
+            (ValueKind::List(list), ValueKind::List(items)) if append => {
+                // an empty list was recorded as a whole
+                if !items.is_empty() {
+                    self.origins.map.remove(path);
+                }
+
+                let offset = list.len();
+
+                for (i, item) in items.iter().enumerate() {
+                    let key = join(path, &(offset + i).to_string());
+                    self.origins.record(&key, item, source);
+                }
+
+                if let ValueKind::List(items) = over.into_kind() {
+                    list.extend(items);
+                }
+            }
+
+            _ => {
+                self.origins.forget(path);
+                self.origins.record(path, &over, source);
+
+                *base = over;
+            }
+        }
+    }
+
+    fn merge_table(&mut self, table: &mut Table, over: Table, path: &str, source: &str) {
+        // an empty table was recorded as a whole
+        if !over.is_empty() {
+            self.origins.map.remove(path);
+        }
+
+        for entry in over {
+            let key = join(path, entry.key());
+
+            match table.get_mut(entry.key()) {
+                Some(value) => self.merge(value, entry.into_value(), &key, source),
+                None => {
+                    self.origins.record(&key, entry.value(), source);
+                    table.insert_entry(entry);
+                }
+            }
+        }
+    }
+}
@@ -1,53 +1,226 @@
 use crate::prelude::*;
 
-use serde_json::Map;
-use serde_json::Number;
-use serde_json::Value;
-use std::str::FromStr;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
 
+use crate::env::EnvResolver;
+use crate::include;
+use crate::include::FileSystem;
+use crate::include::OsFileSystem;
+use crate::lexer::strip_bom;
+use crate::lexer::unescape;
+use crate::lexer::Lexer;
+use crate::merge::DuplicateKeys;
+use crate::merge::ListMerge;
+use crate::merge::Merger;
+use crate::merge::Origins;
+use crate::reference;
+use crate::reference::Reference;
+use crate::template;
+use crate::template::Template;
 use crate::token::DelimiterKind;
 use crate::token::IdentifierKind;
 use crate::token::LiteralKind;
+use crate::token::Location;
+use crate::token::Position;
+use crate::token::ReferenceKind;
+use crate::token::Token;
 use crate::token::TokenKind;
+use crate::value::Entry;
+use crate::value::List;
+use crate::value::Number;
+use crate::value::StringKind;
+use crate::value::Table;
+use crate::value::Value;
+use crate::value::ValueKind;
 
-pub fn bytes_to_str(_bytes: &[u8]) -> &str {
-    unsafe { std::str::from_utf8_unchecked(_bytes) }
+/// Text of token bytes, which the lexer already checked to be valid UTF-8.
+pub fn bytes_to_str(bytes: &[u8]) -> &str {
+    std::str::from_utf8(bytes).expect("tokens hold valid UTF-8")
 }
 
-pub fn bytes_to_string(_bytes: &[u8]) -> String {
-    unsafe { String::from_utf8_unchecked(_bytes.to_vec()) }
+/// Owned text of unescaped string bytes, replacing anything that is not
+/// valid UTF-8 rather than trusting it.
+pub fn bytes_to_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
 }
 
-pub fn unescape_bytes(_bytes: &[u8]) -> Vec<u8> {
-    let mut output = Vec::with_capacity(_bytes.len());
-    let mut chars = _bytes.iter();
-
-    while let Some(&c) = chars.next() {
-        match c {
-            b'\\' => {
-                if let Some(&b) = chars.next() {
-                    match b {
-                        b'n' => output.push(b'\n'),
-                        b'r' => output.push(b'\r'),
-                        b't' => output.push(b'\t'),
-                        b'"' => output.push(b),
-                        b'\'' => output.push(b),
-                        b'\\' => output.push(b),
-                        b'`' => output.push(b),
-                        _ => output.push(b),
-                    }
-                }
+/// Bytes of a string with its escapes replaced. Unknown and broken escapes
+/// lose their backslash, the lexer rejecting them in strict mode.
+pub fn unescape_bytes(bytes: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while let Some(&b) = bytes.get(i) {
+        if b != b'\\' {
+            output.push(b);
+            i += 1;
+            continue;
+        }
+
+        match unescape(&bytes[i + 1..]) {
+            Ok((c, len)) => {
+                output.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                i += 1 + len;
             }
-            _ => output.push(c),
+            Err(_) => i += 1,
         }
     }
 
     output
 }
 
-#[derive(Default, Debug)]
+/// Number out of the text of a number token, which is kept as written once
+/// it is known to fit.
+pub fn create_number(literal: &str) -> std::result::Result<Number, String> {
+    let text = literal.replace('_', "");
+    let text = text.as_str();
+
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+
+    let radix = match digits.get(..2) {
+        Some("0x") => 16,
+        Some("0o") => 8,
+        Some("0b") => 2,
+        _ => {
+            return match text.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(Number::new(literal)),
+                Ok(_) => Err("number is too large".to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        }
+    };
+
+    let n = u64::from_str_radix(&digits[2..], radix).map_err(|e| e.to_string())?;
+
+    match negative && 0i64.checked_sub_unsigned(n).is_none() {
+        true => Err("number too small to fit in target type".to_string()),
+        false => Ok(Number::new(literal)),
+    }
+}
+
+pub fn create_string(bytes: &[u8]) -> String {
+    if bytes.contains(&b'\\') {
+        bytes_to_string(&unescape_bytes(bytes))
+    } else {
+        bytes_to_string(bytes)
+    }
+}
+
+#[derive(Default)]
 pub struct Parser {
     index: usize,
+    recovering: bool,
+    errors: Vec<Error>,
+    /// Keys and list indices leading to the value being parsed.
+    path: Vec<String>,
+    templates: Vec<Template>,
+    variables: Table,
+    env: Option<Rc<dyn EnvResolver>>,
+    fs: Option<Rc<dyn FileSystem>>,
+    /// File being parsed, included files are looked up next to it.
+    file: Option<PathBuf>,
+    /// Files being parsed, from the outermost to the current one.
+    includes: Vec<PathBuf>,
+    /// Values named with `&name`, along with their path.
+    anchors: HashMap<String, (Value, Vec<String>)>,
+    /// Anchors whose value is being parsed.
+    defining: Vec<String>,
+    references: Vec<Reference>,
+    /// Merges `extends` tables and tracks where their values came from.
+    merger: Merger,
+    /// Whether unknown escapes in strings are errors.
+    strict: bool,
+    /// Tables that only dotted keys defined so far, by the offset of the
+    /// key that created them.
+    implicit: HashSet<usize>,
+    duplicates: DuplicateKeys,
+    /// Only checks the syntax, leaving `@include` files unread and
+    /// references and placeholders unresolved.
+    syntax_only: bool,
+}
+
+impl std::fmt::Debug for Parser {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Parser")
+            .field("index", &self.index)
+            .field("recovering", &self.recovering)
+            .field("errors", &self.errors)
+            .field("path", &self.path)
+            .field("templates", &self.templates)
+            .field("variables", &self.variables)
+            .field("env", &self.env.is_some())
+            .field("fs", &self.fs.is_some())
+            .field("file", &self.file)
+            .field("includes", &self.includes)
+            .field("anchors", &self.anchors)
+            .field("defining", &self.defining)
+            .field("references", &self.references)
+            .field("merger", &self.merger)
+            .field("strict", &self.strict)
+            .field("implicit", &self.implicit)
+            .field("duplicates", &self.duplicates)
+            .field("syntax_only", &self.syntax_only)
+            .finish()
+    }
+}
+
+/// Zero width location right after the last token, used when input ends early.
+fn eof(tokens: &[TokenKind]) -> Option<Location> {
+    tokens
+        .last()
+        .map(|t| Location::new(*t.loc().end(), *t.loc().end()))
+}
+
+/// Checks that `source` is well formed, without reading the files it
+/// includes or resolving its references and placeholders, which may need
+/// the environment, as tools that only rewrite the text need.
+pub(crate) fn check_syntax(source: &[u8]) -> Result<()> {
+    let mut parser = Parser {
+        syntax_only: true,
+        ..Parser::new()
+    };
+
+    let tokens = parser.lexer().tokenize(source)?;
+
+    parser.parse(&tokens).map(|_| ())
+}
+
+/// How many templates and references were registered before a value, so
+/// that those of a value dropped as a duplicate can be told apart.
+#[derive(Clone, Copy)]
+struct Mark {
+    templates: usize,
+    references: usize,
+}
+
+/// Error for a closing delimiter that does not close anything.
+fn closing(token: &TokenKind) -> Option<Error> {
+    match token {
+        TokenKind::Delimiter(DelimiterKind::TableTerm(_))
+        | TokenKind::Delimiter(DelimiterKind::ListTerm(_)) => Some(Error::at(
+            ErrorKind::UnexpectedDelimiter,
+            format!("unexpected closing {}", token),
+            *token.loc(),
+        )),
+        _ => None,
+    }
+}
+
+fn unclosed(tokens: &[TokenKind], delimiter: &str, open: Location) -> Error {
+    Error {
+        kind: ErrorKind::UnclosedDelimiter,
+        desc: format!("unclosed '{}' opened at {}", delimiter, open),
+        loc: eof(tokens),
+        file: None,
+    }
 }
 
 impl Parser {
@@ -59,97 +232,902 @@ impl Parser {
         self.index += 1;
     }
 
+    /// Skips the tokens of a broken entry that started at `start`, stopping
+    /// at the next key or at the delimiter closing the current table or list.
+    fn synchronize(&mut self, tokens: &[TokenKind], start: usize) {
+        let mut depth = 0;
+
+        // step over the offending token, unless it opens a nested table or
+        // list, or legitimately closes the current one
+        match tokens.get(self.index()) {
+            Some(TokenKind::Delimiter(DelimiterKind::TablePrec(_)))
+            | Some(TokenKind::Delimiter(DelimiterKind::ListPrec(_))) => {}
+
+            Some(TokenKind::Delimiter(DelimiterKind::TableTerm(_)))
+            | Some(TokenKind::Delimiter(DelimiterKind::ListTerm(_)))
+                if self.index() > start => {}
+
+            _ => self.next(),
+        }
+
+        while let Some(token) = tokens.get(self.index()) {
+            match token {
+                TokenKind::Delimiter(DelimiterKind::TablePrec(_))
+                | TokenKind::Delimiter(DelimiterKind::ListPrec(_)) => depth += 1,
+
+                TokenKind::Delimiter(DelimiterKind::TableTerm(_))
+                | TokenKind::Delimiter(DelimiterKind::ListTerm(_)) => {
+                    if depth == 0 {
+                        break;
+                    }
+
+                    depth -= 1;
+                }
+
+                TokenKind::Identifier(_) | TokenKind::Directive(_) if depth == 0 => break,
+
+                _ => {}
+            }
+
+            self.next();
+        }
+    }
+
+    /// Records `error` and resynchronizes when recovering, fails otherwise.
+    fn recover(&mut self, tokens: &[TokenKind], start: usize, error: Error) -> Result<()> {
+        if !self.recovering {
+            return Err(error);
+        }
+
+        self.errors.push(error);
+        self.synchronize(tokens, start);
+
+        Ok(())
+    }
+
+    fn create_entry<'a>(&mut self, tokens: &'a [TokenKind<'a>], values: &mut Table) -> Result<()> {
+        if let Some(TokenKind::Directive(t)) = tokens.get(self.index()) {
+            return self.create_directive(tokens, t, values);
+        }
+
+        let keys = self.create_keys(tokens)?;
+        self.next();
+
+        let mark = self.mark();
+        let depth = self.path.len();
+        self.path.extend(keys.iter().map(|(key, _)| key.clone()));
+        let value = self.create_value(tokens);
+        self.path.truncate(depth);
+
+        let value = value?;
+        self.next();
+
+        // the entry is over, so the parser carries on with the next one
+        if let Err(e) = self.insert_dotted(values, keys, value, mark) {
+            self.report(e)?;
+        }
+
+        Ok(())
+    }
+
+    /// Keys of the entry under the cursor with their locations, several of
+    /// them for a dotted key such as `server.http.port`.
+    fn create_keys<'a>(
+        &mut self,
+        tokens: &'a [TokenKind<'a>],
+    ) -> Result<Vec<(String, Option<Location>)>> {
+        let Some(TokenKind::Identifier(IdentifierKind::String(t))) = tokens.get(self.index())
+        else {
+            let loc = tokens.get(self.index()).map(|t| *t.loc());
+            return Ok(vec![(self.create_key(tokens)?, loc)]);
+        };
+
+        let start = t.loc().start();
+        let mut offset = 0;
+
+        let keys = bytes_to_str(t.bytes())
+            .split('.')
+            .map(|key| {
+                let at = |offset: usize| {
+                    Position::new(
+                        start.line(),
+                        start.column() + offset,
+                        start.index() + offset,
+                    )
+                };
+
+                let loc = Location::new(at(offset), at(offset + key.len()));
+                offset += key.len() + 1;
+
+                (key.to_string(), Some(loc))
+            })
+            .collect();
+
+        Ok(keys)
+    }
+
+    /// Inserts `value` under the dotted `keys`, creating the tables leading
+    /// to it, or descending into them when they already exist.
+    fn insert_dotted(
+        &mut self,
+        values: &mut Table,
+        mut keys: Vec<(String, Option<Location>)>,
+        value: Value,
+        mark: Mark,
+    ) -> Result<()> {
+        let (key, loc) = keys.pop().expect("an entry has a key");
+
+        let mut path = self.path.clone();
+        let mut table = values;
+
+        for (key, loc) in keys {
+            path.push(key.clone());
+
+            if !table.contains_key(&key) {
+                let inner = match loc {
+                    Some(loc) => {
+                        self.implicit.insert(loc.start().index());
+                        Value::with_loc(ValueKind::Table(Table::new()), loc)
+                    }
+                    None => Value::new(ValueKind::Table(Table::new())),
+                };
+
+                table.insert_entry(Entry::new(key.clone(), loc, inner));
+            }
+
+            let inner = table.get_mut(&key).expect("the table was just inserted");
+            let type_name = inner.type_name();
+
+            table = match inner.as_table_mut() {
+                Some(inner) => inner,
+                None => {
+                    return Err(Error {
+                        kind: ErrorKind::InvalidType,
+                        desc: format!(
+                            "cannot set a key inside '{}', it is a {} and not a table",
+                            path.join("."),
+                            type_name
+                        ),
+                        loc,
+                        file: None,
+                    })
+                }
+            };
+        }
+
+        path.push(key.clone());
+
+        self.insert(table, &mut path, Entry::new(key, loc, value), mark)
+    }
+
+    /// Inserts `entry`, found at `path`, into `table` following the
+    /// duplicate key policy. A table always merges into one that only
+    /// dotted keys defined until then, which then counts as defined.
+    fn insert(
+        &mut self,
+        table: &mut Table,
+        path: &mut Vec<String>,
+        entry: Entry,
+        mark: Mark,
+    ) -> Result<()> {
+        let Some(existing) = table.entry(entry.key()) else {
+            table.insert_entry(entry);
+            return Ok(());
+        };
+
+        let tables = existing.value().as_table().is_some() && entry.value().as_table().is_some();
+
+        let implicit = tables
+            && existing
+                .value()
+                .loc()
+                .is_some_and(|loc| self.implicit.contains(&loc.start().index()));
+
+        match self.duplicates {
+            _ if implicit => {}
+            DuplicateKeys::MergeTables if tables => {}
+
+            DuplicateKeys::Error => {
+                let desc = match existing.key_loc() {
+                    Some(first) => format!(
+                        "duplicate key '{}', first defined at {}",
+                        path.join("."),
+                        first
+                    ),
+                    None => format!("duplicate key '{}'", path.join(".")),
+                };
+
+                return Err(Error {
+                    kind: ErrorKind::DuplicateKey,
+                    desc,
+                    loc: entry.key_loc().copied(),
+                    file: None,
+                });
+            }
+            DuplicateKeys::FirstWins => {
+                self.discard(path, mark, true);
+                return Ok(());
+            }
+            DuplicateKeys::LastWins | DuplicateKeys::MergeTables => {
+                self.discard(path, mark, false);
+                table.insert_entry(entry);
+                return Ok(());
+            }
+        }
+
+        let existing = table.get_mut(entry.key()).expect("the table exists");
+
+        if let Some(loc) = existing.loc() {
+            self.implicit.remove(&loc.start().index());
+        }
+
+        if let (Some(existing), ValueKind::Table(entries)) =
+            (existing.as_table_mut(), entry.into_value().into_kind())
+        {
+            for entry in entries {
+                path.push(entry.key().to_string());
+                let result = self.insert(existing, path, entry, mark);
+                path.pop();
+
+                result?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn mark(&self) -> Mark {
+        Mark {
+            templates: self.templates.len(),
+            references: self.references.len(),
+        }
+    }
+
+    /// Forgets the templates and references of the value at `path` that
+    /// lost to a duplicate: the one registered since `mark` when `fresh`,
+    /// the earlier one otherwise.
+    fn discard(&mut self, path: &[String], mark: Mark, fresh: bool) {
+        let mut i = 0;
+        self.templates.retain(|t| {
+            i += 1;
+            (i > mark.templates) != fresh || !reference::within(&t.path, path)
+        });
+
+        let mut i = 0;
+        self.references.retain(|r| {
+            i += 1;
+            (i > mark.references) != fresh || !reference::within(&r.path, path)
+        });
+    }
+
+    /// Parses the value following `&name` and remembers it under that name.
+    fn create_anchor<'a>(&mut self, tokens: &'a [TokenKind<'a>], anchor: &Token) -> Result<Value> {
+        let name = bytes_to_str(&anchor.bytes()[1..]).to_string();
+
+        self.next(); // skip anchor
+
+        self.defining.push(name.clone());
+        let value = self.create_value(tokens);
+        self.defining.pop();
+
+        let value = value?;
+
+        self.anchors
+            .insert(name, (value.clone(), self.path.clone()));
+
+        Ok(value)
+    }
+
+    /// Parses the table following `extends base`, which gets merged over a
+    /// copy of `base` once the whole document is parsed.
+    fn create_extends<'a>(
+        &mut self,
+        tokens: &'a [TokenKind<'a>],
+        extends: &Token,
+    ) -> Result<Value> {
+        let target = reference::segments(bytes_to_str(&extends.bytes()[7..]).trim_start());
+
+        self.next(); // skip extends
+
+        let value = self.create_value(tokens)?;
+
+        self.references.push(Reference {
+            target,
+            path: self.path.clone(),
+            loc: *extends.loc(),
+            file: self.file_name(),
+            extends: true,
+        });
+
+        Ok(value)
+    }
+
+    /// Copies the value anchored under the name of `*name`.
+    fn create_alias(&mut self, alias: &Token) -> Result<Value> {
+        let name = &bytes_to_str(alias.bytes())[1..];
+
+        if self.defining.iter().any(|d| d == name) {
+            return Err(Error::at(
+                ErrorKind::ReferenceCycle,
+                format!("anchor '&{}' is used inside its own definition", name),
+                *alias.loc(),
+            ));
+        }
+
+        let (mut value, from) = match self.anchors.get(name) {
+            Some((value, from)) => (value.clone(), from.clone()),
+            // the anchor may come from a file that was not included
+            None if self.syntax_only => return Ok(Value::new(ValueKind::Null)),
+            None => {
+                return Err(Error::at(
+                    ErrorKind::UnresolvedReference,
+                    format!(
+                        "cannot resolve alias '*{}', no anchor '&{}' comes before it",
+                        name, name
+                    ),
+                    *alias.loc(),
+                ))
+            }
+        };
+
+        value.set_loc(Some(*alias.loc()));
+
+        // templates and references inside the value apply to the copy too
+        let to = self.path.clone();
+
+        reference::copy_templates(&mut self.templates, &from, &to);
+
+        let references: Vec<Reference> = self
+            .references
+            .iter()
+            .filter(|r| reference::within(&r.path, &from))
+            .map(|r| Reference {
+                path: reference::rebase(&r.path, &from, &to),
+                ..r.clone()
+            })
+            .collect();
+
+        self.references.extend(references);
+
+        Ok(value)
+    }
+
+    /// Handles `@include "file.mcl"`, merging the entries of the file into
+    /// `values`.
+    fn create_directive<'a>(
+        &mut self,
+        tokens: &'a [TokenKind<'a>],
+        directive: &Token,
+        values: &mut Table,
+    ) -> Result<()> {
+        if directive.bytes() != b"@include" {
+            return Err(Error::at(
+                ErrorKind::UnknownDirective,
+                format!("unknown directive '{}'", bytes_to_str(directive.bytes())),
+                *directive.loc(),
+            ));
+        }
+
+        self.next();
+
+        let (target, loc) = match tokens.get(self.index()) {
+            Some(TokenKind::Literal(LiteralKind::String(t))) => {
+                (create_string(t.bytes()), *t.loc())
+            }
+            Some(token) => {
+                return Err(Error::at(
+                    ErrorKind::UnexpectedToken,
+                    format!("expected a file name after '@include', found {}", token),
+                    *token.loc(),
+                ))
+            }
+            None => {
+                return Err(Error {
+                    kind: ErrorKind::MissingValue,
+                    desc: "expected a file name after '@include'".to_string(),
+                    loc: eof(tokens),
+                    file: None,
+                })
+            }
+        };
+
+        if self.syntax_only {
+            self.next();
+            return Ok(());
+        }
+
+        let mark = self.mark();
+
+        // a failed include leaves the parser on its file name, which the
+        // recovery steps over
+        let included = self.include(&target, loc)?;
+
+        self.next();
+
+        for entry in included {
+            let mut path = self.path.clone();
+            path.push(entry.key().to_string());
+
+            if let Err(e) = self.insert(values, &mut path, entry, mark) {
+                self.report(e)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Parses the file included as `target` at `loc`.
+    fn include(&mut self, target: &str, loc: Location) -> Result<Table> {
+        let fs = match &self.fs {
+            Some(fs) => fs.clone(),
+            None => {
+                return Err(Error::at(
+                    ErrorKind::Io,
+                    format!(
+                        "cannot include '{}', no file system was given to the parser",
+                        target
+                    ),
+                    loc,
+                ))
+            }
+        };
+
+        let path = include::resolve(self.file.as_deref(), target);
+        let name = path.display().to_string();
+
+        if let Some(i) = self.includes.iter().position(|p| *p == path) {
+            let mut cycle: Vec<String> = self.includes[i..]
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect();
+
+            cycle.push(name);
+
+            return Err(Error::at(
+                ErrorKind::IncludeCycle,
+                format!("include cycle: {}", cycle.join(" -> ")),
+                loc,
+            ));
+        }
+
+        let source = fs.read(&path).map_err(|e| {
+            Error::at(
+                ErrorKind::Io,
+                format!("cannot include '{}': {}", name, e),
+                loc,
+            )
+        })?;
+
+        let mut includes = self.includes.clone();
+        includes.push(path.clone());
+
+        let mut parser = Parser {
+            recovering: self.recovering,
+            strict: self.strict,
+            duplicates: self.duplicates,
+            path: self.path.clone(),
+            fs: Some(fs),
+            file: Some(path),
+            includes,
+            anchors: std::mem::take(&mut self.anchors),
+            ..Parser::new()
+        };
+
+        let value = match self.recovering {
+            true => {
+                let (tokens, errors) = parser.lexer().tokenize_recovering(strip_bom(&source));
+                parser.errors.extend(errors);
+                parser.document(&tokens)
+            }
+            false => parser
+                .lexer()
+                .tokenize(strip_bom(&source))
+                .and_then(|tokens| parser.document(&tokens)),
+        };
+
+        // the anchors come back even when the file is broken
+        self.anchors = std::mem::take(&mut parser.anchors);
+
+        let value = value.map_err(|e| e.in_file(Some(&name)))?;
+        self.templates.append(&mut parser.templates);
+        self.references.append(&mut parser.references);
+        self.errors
+            .extend(parser.errors.into_iter().map(|e| e.in_file(Some(&name))));
+
+        match value.into_kind() {
+            ValueKind::Table(table) => Ok(table),
+            kind => Err(Error::at(
+                ErrorKind::InvalidType,
+                format!(
+                    "cannot include '{}', it holds a {} instead of a table",
+                    name,
+                    Value::new(kind).type_name()
+                ),
+                loc,
+            )),
+        }
+    }
+
+    fn create_item<'a>(&mut self, tokens: &'a [TokenKind<'a>], values: &mut List) -> Result<()> {
+        self.path.push(values.len().to_string());
+        let value = self.create_value(tokens);
+        self.path.pop();
+
+        let value = value?;
+        self.next();
+
+        values.push(value);
+
+        Ok(())
+    }
+
+    /// Location from `open` to the token under the cursor, the closing
+    /// delimiter of a table or list unless the input ended early.
+    fn span(&self, tokens: &[TokenKind], open: Location) -> Location {
+        match tokens.get(self.index()).or(tokens.last()) {
+            Some(close) => Location::new(*open.start(), *close.loc().end()),
+            None => open,
+        }
+    }
+
     pub fn new() -> Self {
-        Parser { index: 0 }
+        Parser {
+            index: 0,
+            recovering: false,
+            errors: Vec::new(),
+            path: Vec::new(),
+            templates: Vec::new(),
+            variables: Table::new(),
+            env: None,
+            fs: None,
+            file: None,
+            includes: Vec::new(),
+            anchors: HashMap::new(),
+            defining: Vec::new(),
+            references: Vec::new(),
+            merger: Merger::default(),
+            strict: false,
+            implicit: HashSet::new(),
+            duplicates: DuplicateKeys::default(),
+            syntax_only: false,
+        }
+    }
+
+    /// Enables `@include "file.mcl"` directives, reading files from `fs`.
+    pub fn with_fs(mut self, fs: impl FileSystem + 'static) -> Self {
+        self.fs = Some(Rc::new(fs));
+        self
+    }
+
+    /// Reads and parses the file at `path`, resolving its includes relative
+    /// to it. Files are read from the disk unless [`Parser::with_fs`] was
+    /// given another file system, and may start with a byte order mark.
+    pub fn parse_file(&mut self, path: impl AsRef<Path>) -> Result<Value> {
+        let path = include::resolve(None, &path.as_ref().to_string_lossy());
+        let fs = self.fs.get_or_insert_with(|| Rc::new(OsFileSystem)).clone();
+
+        let name = path.display().to_string();
+
+        let source = fs
+            .read(&path)
+            .map_err(|e| Error::new(ErrorKind::Io, format!("cannot read '{}': {}", name, e)))?;
+
+        self.set_file(path);
+
+        let tokens = self
+            .lexer()
+            .tokenize(strip_bom(&source))
+            .map_err(|e| e.in_file(Some(&name)))?;
+
+        self.parse(&tokens)
+    }
+
+    /// Names the document being parsed, which errors then point to and
+    /// includes are resolved against.
+    pub fn with_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.set_file(include::resolve(None, &path.as_ref().to_string_lossy()));
+        self
+    }
+
+    fn set_file(&mut self, path: PathBuf) {
+        self.includes = vec![path.clone()];
+        self.file = Some(path);
+    }
+
+    fn file_name(&self) -> Option<String> {
+        self.file.as_ref().map(|f| f.display().to_string())
+    }
+
+    /// Enables `${env:NAME}` and `${env:NAME:-default}` placeholders in
+    /// template strings, looking variables up in `resolver`.
+    pub fn with_env(mut self, resolver: impl EnvResolver + 'static) -> Self {
+        self.env = Some(Rc::new(resolver));
+        self
+    }
+
+    /// How lists of an `extends` table combine with those of its base,
+    /// replacing them by default.
+    pub fn with_list_merge(mut self, lists: ListMerge) -> Self {
+        self.merger = Merger::new(lists);
+        self
+    }
+
+    /// What happens to a key written twice in the same table, an error by
+    /// default.
+    pub fn with_duplicate_keys(mut self, duplicates: DuplicateKeys) -> Self {
+        self.duplicates = duplicates;
+        self
+    }
+
+    /// Rejects unknown escapes such as `\q` in strings, see
+    /// [`Lexer::with_strict_escapes`].
+    pub fn with_strict_escapes(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    fn lexer(&self) -> Lexer {
+        Lexer::new().with_strict_escapes(self.strict)
+    }
+
+    /// Where the values of `extends` tables came from after parsing, either
+    /// the path of their base or that of the table itself.
+    pub fn origins(&self) -> &Origins {
+        self.merger.origins()
+    }
+
+    /// Lexes and parses `source`, see [`Parser::parse`].
+    pub fn parse_slice(&mut self, source: &[u8]) -> Result<Value> {
+        let tokens = self
+            .lexer()
+            .tokenize(source)
+            .map_err(|e| e.in_file(self.file_name().as_deref()))?;
+
+        self.parse(&tokens)
+    }
+
+    /// Values that `${name}` placeholders fall back to when the document
+    /// itself has nothing at that path.
+    pub fn with_variables(mut self, variables: Table) -> Self {
+        self.variables = variables;
+        self
+    }
+
+    /// Parses as much of `tokens` as possible, skipping broken entries
+    /// instead of stopping at the first one.
+    ///
+    /// Returns the partial value together with every error encountered.
+    pub fn parse_recovering(&mut self, tokens: &[TokenKind]) -> (Value, Vec<Error>) {
+        self.recovering = true;
+
+        let value = match self.parse(tokens) {
+            Ok(value) => value,
+            Err(e) => {
+                self.errors.push(e);
+                Value::new(ValueKind::Null)
+            }
+        };
+
+        self.recovering = false;
+
+        (value, std::mem::take(&mut self.errors))
+    }
+
+    /// Reports `error` when recovering, fails otherwise.
+    fn report(&mut self, error: Error) -> Result<()> {
+        if !self.recovering {
+            return Err(error);
+        }
+
+        self.errors.push(error);
+
+        Ok(())
     }
 
     pub fn parse(&mut self, tokens: &[TokenKind]) -> Result<Value> {
-        match tokens.get(self.index()) {
+        let file = self.file_name();
+
+        let result = self.document(tokens).and_then(|mut value| {
+            if !self.syntax_only {
+                self.dereference(&mut value)?;
+                self.interpolate(&mut value)?;
+            }
+
+            Ok(value)
+        });
+
+        for error in self.errors.iter_mut() {
+            if error.file.is_none() {
+                error.file.clone_from(&file);
+            }
+        }
+
+        result.map_err(|e| e.in_file(file.as_deref()))
+    }
+
+    /// Replaces every `$ref` of the document with a copy of its target.
+    fn dereference(&mut self, value: &mut Value) -> Result<()> {
+        let references = std::mem::take(&mut self.references);
+
+        for error in reference::resolve(value, &references, &mut self.templates, &mut self.merger) {
+            self.report(error)?;
+        }
+
+        Ok(())
+    }
+
+    /// Fills in the placeholders of every template string of the document.
+    fn interpolate(&mut self, value: &mut Value) -> Result<()> {
+        let templates = std::mem::take(&mut self.templates);
+        let variables = Value::new(ValueKind::Table(std::mem::take(&mut self.variables)));
+
+        let errors = template::interpolate(value, &templates, &variables, self.env.as_deref());
+
+        if let ValueKind::Table(variables) = variables.into_kind() {
+            self.variables = variables;
+        }
+
+        for error in errors {
+            self.report(error)?;
+        }
+
+        Ok(())
+    }
+
+    fn document(&mut self, tokens: &[TokenKind]) -> Result<Value> {
+        let value = match tokens.get(self.index()) {
             Some(token) => match token {
-                TokenKind::Delimiter(DelimiterKind::TablePrec) => self.create_table(tokens),
+                TokenKind::Delimiter(DelimiterKind::TablePrec(_)) => self.create_table(tokens)?,
 
-                TokenKind::Delimiter(DelimiterKind::ListPrec) => self.create_list(tokens),
+                TokenKind::Delimiter(DelimiterKind::ListPrec(_)) => self.create_list(tokens)?,
 
-                TokenKind::Identifier(IdentifierKind::String(_)) => {
-                    let mut values = Map::with_capacity(tokens.len());
+                TokenKind::Identifier(IdentifierKind::String(_)) | TokenKind::Directive(_) => {
+                    let mut values = Table::new();
 
-                    while tokens.get(self.index()).is_some() {
-                        let key = self.create_key(tokens)?;
-                        self.next();
-                        let value = self.create_value(tokens)?;
-                        self.next();
+                    while let Some(token) = tokens.get(self.index()) {
+                        let start = self.index();
 
-                        values.insert(key, value);
+                        let result = match closing(token) {
+                            Some(e) => Err(e),
+                            None => self.create_entry(tokens, &mut values),
+                        };
+
+                        if let Err(e) = result {
+                            self.recover(tokens, start, e)?;
+                        }
                     }
 
-                    Ok(Value::Object(values))
+                    return Ok(Value::new(ValueKind::Table(values)));
                 }
                 _ => {
-                    let mut values = Vec::with_capacity(tokens.len());
+                    let mut values = List::new();
+
+                    while let Some(token) = tokens.get(self.index()) {
+                        let start = self.index();
 
-                    while tokens.get(self.index()).is_some() {
-                        let value = self.create_value(tokens)?;
-                        self.next();
+                        let result = match closing(token) {
+                            Some(e) => Err(e),
+                            None => self.create_item(tokens, &mut values),
+                        };
 
-                        values.push(value);
+                        if let Err(e) = result {
+                            self.recover(tokens, start, e)?;
+                        }
                     }
 
-                    Ok(Value::Array(values))
+                    return Ok(Value::new(ValueKind::List(values)));
                 }
             },
-            None => Err(Error {
-                desc: "ran out of tokens".to_string(),
-            }),
+            None => return Err(Error::new(ErrorKind::UnexpectedEof, "ran out of tokens")),
+        };
+
+        self.next(); // skip closing delimiter
+
+        while let Some(token) = tokens.get(self.index()) {
+            let error = match closing(token) {
+                Some(e) => e,
+                None => Error::at(
+                    ErrorKind::UnexpectedToken,
+                    format!("expected end of input, found {}", token),
+                    *token.loc(),
+                ),
+            };
+
+            self.report(error)?;
+            self.next();
         }
+
+        Ok(value)
     }
 
+    /// Creates a list, starting at its opening `[` and stopping on its `]`.
     pub fn create_list<'a>(&mut self, tokens: &'a [TokenKind<'a>]) -> Result<Value> {
-        let mut values = Vec::new();
+        let open = *tokens[self.index()].loc();
+        let mut values = List::new();
 
-        while let Some(token) = tokens.get(self.index()) {
-            match token {
-                TokenKind::Delimiter(DelimiterKind::ListTerm) => break,
-                _ => {
-                    let value = self.create_value(tokens)?;
+        self.next(); // skip opening "["
+
+        loop {
+            match tokens.get(self.index()) {
+                Some(TokenKind::Delimiter(DelimiterKind::ListTerm(_))) => break,
 
-                    self.next();
+                // the "}" belongs to the table holding the list, so the list
+                // stops on the token before it
+                Some(TokenKind::Delimiter(DelimiterKind::TableTerm(_))) => {
+                    self.report(Error {
+                        loc: Some(open),
+                        ..unclosed(tokens, "[", open)
+                    })?;
+                    self.index -= 1;
+                    break;
+                }
 
-                    values.push(value);
+                Some(_) => {
+                    let start = self.index();
+
+                    if let Err(e) = self.create_item(tokens, &mut values) {
+                        self.recover(tokens, start, e)?;
+                    }
+                }
+
+                None => {
+                    self.report(unclosed(tokens, "[", open))?;
+                    break;
                 }
             }
         }
 
-        Ok(Value::Array(values))
+        Ok(Value::with_loc(
+            ValueKind::List(values),
+            self.span(tokens, open),
+        ))
     }
 
+    /// Creates a table, starting at its opening `{` and stopping on its `}`.
     pub fn create_table<'a>(&mut self, tokens: &'a [TokenKind<'a>]) -> Result<Value> {
-        let mut values = Map::new();
+        let open = *tokens[self.index()].loc();
+        let mut values = Table::new();
 
-        while let Some(token) = tokens.get(self.index()) {
-            match token {
-                TokenKind::Delimiter(DelimiterKind::TableTerm) => break,
+        self.next(); // skip opening "{"
 
-                _ => {
-                    let key = self.create_key(tokens)?;
-                    self.next();
+        loop {
+            match tokens.get(self.index()) {
+                Some(TokenKind::Delimiter(DelimiterKind::TableTerm(_))) => break,
+
+                Some(token) => {
+                    let start = self.index();
+
+                    let result = match token {
+                        TokenKind::Delimiter(DelimiterKind::ListTerm(_)) => {
+                            Err(closing(token).unwrap())
+                        }
+                        _ => self.create_entry(tokens, &mut values),
+                    };
 
-                    let value = self.create_value(tokens)?;
-                    self.next();
+                    if let Err(e) = result {
+                        self.recover(tokens, start, e)?;
+                    }
+                }
 
-                    values.insert(key, value);
+                None => {
+                    self.report(unclosed(tokens, "{", open))?;
+                    break;
                 }
             }
         }
 
-        Ok(Value::Object(values))
+        Ok(Value::with_loc(
+            ValueKind::Table(values),
+            self.span(tokens, open),
+        ))
     }
 
     pub fn create_key<'a>(&mut self, tokens: &'a [TokenKind<'a>]) -> Result<String> {
         if let Some(token) = tokens.get(self.index()) {
             match token {
-                TokenKind::Literal(LiteralKind::String(t)) => {
-                    let result = bytes_to_string(t.bytes());
-                    Ok(result)
+                TokenKind::Literal(LiteralKind::String(t))
+                | TokenKind::Literal(LiteralKind::TemplateString(t)) => {
+                    Ok(create_string(t.bytes()))
                 }
 
                 TokenKind::Identifier(IdentifierKind::String(t)) => {
@@ -157,79 +1135,122 @@ impl Parser {
                     Ok(result)
                 }
 
-                token => Err(Error {
-                    desc: format!("invalid key '{:?}'", token),
-                }),
+                token => Err(Error::at(
+                    ErrorKind::UnexpectedToken,
+                    format!("expected a key, found {}", token),
+                    *token.loc(),
+                )),
             }
         } else {
             Err(Error {
+                kind: ErrorKind::MissingKey,
                 desc: "expected a key".to_string(),
+                loc: eof(tokens),
+                file: None,
             })
         }
     }
 
     pub fn create_value<'a>(&mut self, tokens: &'a [TokenKind<'a>]) -> Result<Value> {
         if let Some(token) = tokens.get(self.index()) {
-            match token {
-                TokenKind::Literal(LiteralKind::True) => Ok(Value::Bool(true)),
+            let loc = *token.loc();
 
-                TokenKind::Literal(LiteralKind::False) => Ok(Value::Bool(false)),
+            let kind = match token {
+                TokenKind::Literal(LiteralKind::True(_)) => ValueKind::Bool(true),
+
+                TokenKind::Literal(LiteralKind::False(_)) => ValueKind::Bool(false),
 
                 TokenKind::Literal(LiteralKind::String(t)) => {
-                    let bytes_str = t.bytes();
-
-                    if bytes_str.contains(&b'\\') {
-                        let unescaped = unescape_bytes(bytes_str);
-                        let result = bytes_to_string(&unescaped);
-                        Ok(Value::String(result))
-                    } else {
-                        let result = bytes_to_string(bytes_str);
-                        Ok(Value::String(result))
+                    ValueKind::String(create_string(t.bytes()), StringKind::Normal)
+                }
+
+                TokenKind::Literal(LiteralKind::TemplateString(t)) => {
+                    if template::has_placeholder(t.bytes()) {
+                        self.templates.push(Template {
+                            path: self.path.clone(),
+                            parts: template::parts(t)?,
+                            file: self.file_name(),
+                        });
                     }
+
+                    ValueKind::String(create_string(t.bytes()), StringKind::Template)
                 }
 
                 TokenKind::Literal(LiteralKind::Number(t)) => {
                     let num_str = bytes_to_str(t.bytes());
 
-                    match Number::from_str(num_str) {
-                        Ok(num) => Ok(Value::Number(num)),
-                        Err(e) => Err(Error {
-                            desc: e.to_string(),
-                        }),
+                    match create_number(num_str) {
+                        Ok(n) => ValueKind::Number(n),
+                        Err(e) => {
+                            return Err(Error::at(
+                                ErrorKind::InvalidNumber,
+                                format!("invalid number '{}': {}", num_str, e),
+                                loc,
+                            ))
+                        }
                     }
                 }
 
-                TokenKind::Literal(LiteralKind::Null) => Ok(Value::Null),
+                TokenKind::Literal(LiteralKind::Null(_)) => ValueKind::Null,
 
-                TokenKind::Delimiter(DelimiterKind::TablePrec) => {
-                    self.next(); // skip opening "{"
+                TokenKind::Reference(ReferenceKind::Anchor(t)) => {
+                    return self.create_anchor(tokens, t)
+                }
 
-                    match self.create_table(tokens) {
-                        Ok(tbl) => Ok(tbl),
-                        Err(e) => Err(Error {
-                            desc: format!("failed creating a table because of {}", e.desc),
-                        }),
-                    }
+                TokenKind::Reference(ReferenceKind::Alias(t)) => return self.create_alias(t),
+
+                TokenKind::Reference(ReferenceKind::Extends(t)) => {
+                    return self.create_extends(tokens, t)
                 }
 
-                TokenKind::Delimiter(DelimiterKind::ListPrec) => {
-                    self.next(); // skip opening "["
+                TokenKind::Reference(ReferenceKind::Ref(t)) => {
+                    let target = bytes_to_str(&t.bytes()[4..]).trim_start();
 
-                    match self.create_list(tokens) {
-                        Ok(ls) => Ok(ls),
-                        Err(e) => Err(Error {
-                            desc: format!("failed creating a list because of {}", e.desc),
-                        }),
-                    }
+                    self.references.push(Reference {
+                        target: reference::segments(target),
+                        path: self.path.clone(),
+                        loc,
+                        file: self.file_name(),
+                        extends: false,
+                    });
+
+                    // filled in once the whole document is parsed
+                    ValueKind::Null
                 }
 
-                token => Err(Error {
-                    desc: format!("invalid value '{:?}'", token),
-                }),
-            }
+                TokenKind::Delimiter(DelimiterKind::TablePrec(_)) => {
+                    return self.create_table(tokens)
+                }
+
+                TokenKind::Delimiter(DelimiterKind::ListPrec(_)) => {
+                    return self.create_list(tokens)
+                }
+
+                TokenKind::Delimiter(DelimiterKind::TableTerm(_))
+                | TokenKind::Delimiter(DelimiterKind::ListTerm(_)) => {
+                    return Err(Error::at(
+                        ErrorKind::MissingValue,
+                        format!("expected a value, found {}", token),
+                        loc,
+                    ))
+                }
+
+                token => {
+                    return Err(Error::at(
+                        ErrorKind::UnexpectedToken,
+                        format!("expected a value, found {}", token),
+                        loc,
+                    ))
+                }
+            };
+
+            Ok(Value::with_loc(kind, loc))
         } else {
             Err(Error {
-                desc: "ran out of tokens".to_string(),
+                kind: ErrorKind::MissingValue,
+                desc: "expected a value".to_string(),
+                loc: eof(tokens),
+                file: None,
             })
         }
     }
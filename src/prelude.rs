@@ -1,3 +1,4 @@
 pub use crate::error::Error;
+pub use crate::error::ErrorKind;
 
 pub type Result<T> = core::result::Result<T, Error>;
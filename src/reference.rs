@@ -0,0 +1,232 @@
+use crate::prelude::*;
+
+use crate::merge::Merger;
+use crate::template::Template;
+use crate::token::Location;
+use crate::value::Table;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+/// A `$ref` or `extends` waiting for the value it points to.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    /// Path of the referenced value.
+    pub target: Vec<String>,
+    /// Path the copy goes to.
+    pub path: Vec<String>,
+    pub loc: Location,
+    /// File the reference was written in, when parsing files.
+    pub file: Option<String>,
+    /// Whether the table at `path` is merged over the copy instead of
+    /// being replaced by it.
+    pub extends: bool,
+}
+
+impl Reference {
+    /// The reference as written, for error messages.
+    fn syntax(&self) -> String {
+        match self.extends {
+            true => format!("extends {}", self.target.join(".")),
+            false => format!("$ref {}", self.target.join(".")),
+        }
+    }
+}
+
+/// Segments of a dot separated path as written after `$ref` or `extends`.
+pub fn segments(path: &str) -> Vec<String> {
+    path.split('.').map(str::to_string).collect()
+}
+
+/// Whether `path` is `prefix` or lies inside of it.
+pub fn within(path: &[String], prefix: &[String]) -> bool {
+    path.starts_with(prefix)
+}
+
+/// Rewrites `path`, which lies within `from`, to the same place within `to`.
+pub fn rebase(path: &[String], from: &[String], to: &[String]) -> Vec<String> {
+    [to, &path[from.len()..]].concat()
+}
+
+/// Copies the templates found within `from` to the same places within `to`,
+/// so that the copy of a value gets interpolated too.
+pub fn copy_templates(templates: &mut Vec<Template>, from: &[String], to: &[String]) {
+    let copies: Vec<Template> = templates
+        .iter()
+        .filter(|t| within(&t.path, from))
+        .map(|t| Template {
+            path: rebase(&t.path, from, to),
+            ..t.clone()
+        })
+        .collect();
+
+    templates.extend(copies);
+}
+
+struct Resolver<'a> {
+    references: &'a [Reference],
+    done: Vec<bool>,
+    stack: Vec<usize>,
+    root: &'a mut Value,
+    templates: &'a mut Vec<Template>,
+    merger: &'a mut Merger,
+}
+
+impl Resolver<'_> {
+    fn resolve(&mut self, i: usize) -> Result<()> {
+        if self.done[i] {
+            return Ok(());
+        }
+
+        let references = self.references;
+        let reference = &references[i];
+
+        self.stack.push(i);
+
+        // references inside the target, or holding it, have to be copied first
+        for j in 0..references.len() {
+            if !within(&references[j].path, &reference.target)
+                && !within(&reference.target, &references[j].path)
+            {
+                continue;
+            }
+
+            if let Some(start) = self.stack.iter().position(|&k| k == j) {
+                let mut cycle: Vec<String> = self.stack[start..]
+                    .iter()
+                    .map(|&k| references[k].path.join("."))
+                    .collect();
+
+                cycle.push(reference.target.join("."));
+
+                self.stack.pop();
+
+                return Err(Error::at(
+                    ErrorKind::ReferenceCycle,
+                    format!("cyclic reference: {}", cycle.join(" -> ")),
+                    reference.loc,
+                )
+                .in_file(reference.file.as_deref()));
+            }
+
+            if let Err(e) = self.resolve(j) {
+                self.stack.pop();
+                return Err(e);
+            }
+        }
+
+        self.stack.pop();
+        self.done[i] = true;
+
+        let mut value = match self.root.lookup(&reference.target) {
+            Some(value) => value.clone(),
+            None => {
+                return Err(Error::at(
+                    ErrorKind::UnresolvedReference,
+                    format!("cannot resolve '{}'", reference.syntax()),
+                    reference.loc,
+                )
+                .in_file(reference.file.as_deref()))
+            }
+        };
+
+        if reference.extends {
+            return self.extend(reference, value);
+        }
+
+        value.set_loc(Some(reference.loc));
+
+        if let Some(slot) = self.root.lookup_mut(&reference.path) {
+            *slot = value;
+        }
+
+        copy_templates(self.templates, &reference.target, &reference.path);
+
+        Ok(())
+    }
+
+    /// Merges the table at the path of `reference` over `base`.
+    fn extend(&mut self, reference: &Reference, base: Value) -> Result<()> {
+        if base.as_table().is_none() {
+            return Err(Error::at(
+                ErrorKind::InvalidType,
+                format!(
+                    "cannot extend '{}', it is a {}",
+                    reference.target.join("."),
+                    base.type_name()
+                ),
+                reference.loc,
+            )
+            .in_file(reference.file.as_deref()));
+        }
+
+        let Some(slot) = self.root.lookup_mut(&reference.path) else {
+            return Ok(());
+        };
+
+        let own = std::mem::replace(slot, Value::new(ValueKind::Table(Table::new())));
+        let loc = own.loc().copied();
+
+        // templates of the base only apply where the table has no value
+        let (from, to) = (&reference.target, &reference.path);
+
+        let copies: Vec<Template> = self
+            .templates
+            .iter()
+            .filter(|t| within(&t.path, from) && t.path.len() > from.len())
+            .filter(|t| own.lookup(&t.path[from.len()..]).is_none())
+            .map(|t| Template {
+                path: rebase(&t.path, from, to),
+                ..t.clone()
+            })
+            .collect();
+
+        self.templates.extend(copies);
+
+        // sources given by tables extended within this one
+        let (from, to) = (from.join("."), to.join("."));
+        let nested = self.merger.origins().within(&to);
+
+        self.merger.merge(slot, base, &to, &from);
+        self.merger.origins_mut().copy(&from, &to);
+        self.merger.merge(slot, own, &to, &to);
+        self.merger.origins_mut().restore(nested);
+
+        slot.set_loc(loc);
+
+        Ok(())
+    }
+}
+
+/// Replaces every `$ref` of `root` with a copy of the value it points to,
+/// and merges every `extends` table over a copy of its base, returning
+/// every error encountered.
+pub fn resolve(
+    root: &mut Value,
+    references: &[Reference],
+    templates: &mut Vec<Template>,
+    merger: &mut Merger,
+) -> Vec<Error> {
+    let mut errors: Vec<Error> = Vec::new();
+
+    let mut resolver = Resolver {
+        references,
+        done: vec![false; references.len()],
+        stack: Vec::new(),
+        root,
+        templates,
+        merger,
+    };
+
+    for i in 0..references.len() {
+        if let Err(e) = resolver.resolve(i) {
+            // a broken reference is reported once, not by every user
+            if !errors.iter().any(|x| x.loc == e.loc) {
+                errors.push(e);
+            }
+
+            resolver.done[i] = true;
+        }
+    }
+
+    errors
+}
@@ -0,0 +1,551 @@
+use regex::Regex;
+
+use crate::prelude::*;
+
+use crate::token::Location;
+use crate::value::Table;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+/// Type a value is allowed to have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Null,
+    Bool,
+    /// A number without fractional part, also accepted where a number is.
+    Integer,
+    Number,
+    String,
+    Table,
+    List,
+}
+
+impl Type {
+    fn from_name(name: &str) -> Option<Type> {
+        match name {
+            "null" => Some(Type::Null),
+            "bool" => Some(Type::Bool),
+            "integer" => Some(Type::Integer),
+            "number" => Some(Type::Number),
+            "string" => Some(Type::String),
+            "table" => Some(Type::Table),
+            "list" => Some(Type::List),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Null => "null",
+            Type::Bool => "bool",
+            Type::Integer => "integer",
+            Type::Number => "number",
+            Type::String => "string",
+            Type::Table => "table",
+            Type::List => "list",
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match (self, value.kind()) {
+            (Type::Null, ValueKind::Null)
+            | (Type::Bool, ValueKind::Bool(_))
+            | (Type::Number, ValueKind::Number(_))
+            | (Type::String, ValueKind::String(..))
+            | (Type::Table, ValueKind::Table(_))
+            | (Type::List, ValueKind::List(_)) => true,
+            (Type::Integer, ValueKind::Number(n)) => n.is_integer(),
+            _ => false,
+        }
+    }
+}
+
+/// Constraints on a value, and on the values it holds.
+///
+/// Schemas are usually written in MCL, see [`Schema::parse`], but can be
+/// built in code too:
+///
+/// ```
+/// use mcl::schema::{Schema, Type};
+///
+/// let schema = Schema::new()
+///     .field("port", Schema::of(Type::Integer).min(1.0).max(65535.0))
+///     .field("hosts", Schema::of(Type::List).min_len(1).items(Schema::of(Type::String)));
+///
+/// let value = mcl::parse(b"port 0 hosts []").unwrap();
+///
+/// assert_eq!(schema.validate(&value).len(), 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    /// Allowed types, any type being allowed when empty.
+    types: Vec<Type>,
+    optional: bool,
+    description: Option<String>,
+    min: Option<f64>,
+    max: Option<f64>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    one_of: Vec<Value>,
+    pattern: Option<Regex>,
+    items: Option<Box<Schema>>,
+    fields: Vec<(String, Schema)>,
+    values: Option<Box<Schema>>,
+    deny_unknown: bool,
+}
+
+impl Schema {
+    /// Schema accepting any value.
+    pub fn new() -> Schema {
+        Schema::default()
+    }
+
+    /// Schema accepting values of type `ty`.
+    pub fn of(ty: Type) -> Schema {
+        Schema {
+            types: vec![ty],
+            ..Schema::default()
+        }
+    }
+
+    /// Accepts values of type `ty` as well, a schema accepting any value
+    /// staying that way.
+    pub fn or(mut self, ty: Type) -> Self {
+        if !self.types.is_empty() {
+            self.types.push(ty);
+        }
+
+        self
+    }
+
+    /// Lets the value be missing from its table.
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Smallest number allowed.
+    pub fn min(mut self, min: f64) -> Self {
+        self.min = Some(min);
+        self
+    }
+
+    /// Largest number allowed.
+    pub fn max(mut self, max: f64) -> Self {
+        self.max = Some(max);
+        self
+    }
+
+    /// Fewest characters of a string, or items of a list or table.
+    pub fn min_len(mut self, min: usize) -> Self {
+        self.min_len = Some(min);
+        self
+    }
+
+    /// Most characters of a string, or items of a list or table.
+    pub fn max_len(mut self, max: usize) -> Self {
+        self.max_len = Some(max);
+        self
+    }
+
+    /// Only accepts values equal to one of `values`.
+    pub fn one_of(mut self, values: impl IntoIterator<Item = Value>) -> Self {
+        self.one_of = values.into_iter().collect();
+        self
+    }
+
+    /// Only accepts strings matching `pattern` somewhere, anchor it with
+    /// `^` and `$` to match whole strings.
+    pub fn pattern(mut self, pattern: Regex) -> Self {
+        self.pattern = Some(pattern);
+        self
+    }
+
+    /// Schema of every item of a list.
+    pub fn items(mut self, schema: Schema) -> Self {
+        self.items = Some(Box::new(schema));
+        self
+    }
+
+    /// Schema of the value under `key` of a table, required unless
+    /// [`Schema::optional`].
+    pub fn field(mut self, key: impl Into<String>, schema: Schema) -> Self {
+        self.fields.push((key.into(), schema));
+        self
+    }
+
+    /// Schema of every value of a table that is not a known field.
+    pub fn values(mut self, schema: Schema) -> Self {
+        self.values = Some(Box::new(schema));
+        self
+    }
+
+    /// Rejects keys of a table that are not known fields.
+    pub fn deny_unknown(mut self) -> Self {
+        self.deny_unknown = true;
+        self
+    }
+
+    pub fn types(&self) -> &[Type] {
+        &self.types
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn fields(&self) -> impl Iterator<Item = (&str, &Schema)> {
+        self.fields.iter().map(|(k, s)| (k.as_str(), s))
+    }
+
+    /// Reads a schema written in MCL, each key of `source` being a field of
+    /// the root table.
+    ///
+    /// A field is either the name of its type, or a table of constraints:
+    ///
+    /// ```text
+    /// name "string"
+    /// port { type "integer" min 1 max 65535 }
+    /// hosts { type "list" min_len 1 items "string" }
+    /// log {
+    ///     optional true
+    ///     fields { level { one_of ["debug" "info" "warn"] } }
+    /// }
+    /// ```
+    ///
+    /// Constraints are `type` (a type name or a list of them), `optional`,
+    /// `description`, `min`, `max`, `min_len`, `max_len`, `one_of`,
+    /// `pattern`, `items`, `fields`, `values` and `deny_unknown`.
+    pub fn parse(source: &[u8]) -> Result<Schema> {
+        let value = crate::parse(source)?;
+
+        match value.as_table() {
+            Some(table) => Ok(Schema {
+                types: vec![Type::Table],
+                fields: fields(table)?,
+                ..Schema::default()
+            }),
+            None => Err(invalid("a schema must be a table of fields", &value)),
+        }
+    }
+
+    /// Checks `value` against the schema, returning every violation located
+    /// at the offending value.
+    pub fn validate(&self, value: &Value) -> Vec<Error> {
+        let mut errors = Vec::new();
+
+        self.check(value, "", &mut errors);
+
+        errors
+    }
+
+    fn check(&self, value: &Value, path: &str, errors: &mut Vec<Error>) {
+        let name = if path.is_empty() { "the root" } else { path };
+
+        if !self.types.is_empty() && !self.types.iter().any(|ty| ty.matches(value)) {
+            let expected: Vec<&str> = self.types.iter().map(Type::name).collect();
+
+            errors.push(violation(
+                format!(
+                    "'{}' must be a {}, found a {}",
+                    name,
+                    expected.join(" or "),
+                    value.type_name()
+                ),
+                value.loc(),
+            ));
+
+            return;
+        }
+
+        if let Some(n) = value.as_f64() {
+            if let Some(min) = self.min.filter(|&min| n < min) {
+                errors.push(violation(
+                    format!("'{}' must be at least {}, found {}", name, min, n),
+                    value.loc(),
+                ));
+            }
+
+            if let Some(max) = self.max.filter(|&max| n > max) {
+                errors.push(violation(
+                    format!("'{}' must be at most {}, found {}", name, max, n),
+                    value.loc(),
+                ));
+            }
+        }
+
+        let len = match value.kind() {
+            ValueKind::String(s, _) => Some(s.chars().count()),
+            ValueKind::List(l) => Some(l.len()),
+            ValueKind::Table(t) => Some(t.len()),
+            _ => None,
+        };
+
+        if let Some(len) = len {
+            if let Some(min) = self.min_len.filter(|&min| len < min) {
+                errors.push(violation(
+                    format!(
+                        "'{}' must have at least {} {}, found {}",
+                        name,
+                        min,
+                        unit(value, min),
+                        len
+                    ),
+                    value.loc(),
+                ));
+            }
+
+            if let Some(max) = self.max_len.filter(|&max| len > max) {
+                errors.push(violation(
+                    format!(
+                        "'{}' must have at most {} {}, found {}",
+                        name,
+                        max,
+                        unit(value, max),
+                        len
+                    ),
+                    value.loc(),
+                ));
+            }
+        }
+
+        if !self.one_of.is_empty() && !self.one_of.contains(value) {
+            let allowed: Vec<String> = self.one_of.iter().map(describe).collect();
+
+            errors.push(violation(
+                format!("'{}' must be one of {}", name, allowed.join(", ")),
+                value.loc(),
+            ));
+        }
+
+        if let (Some(pattern), Some(s)) = (&self.pattern, value.as_str()) {
+            if !pattern.is_match(s) {
+                errors.push(violation(
+                    format!("'{}' must match the pattern '{}'", name, pattern),
+                    value.loc(),
+                ));
+            }
+        }
+
+        match value.kind() {
+            ValueKind::List(list) => {
+                if let Some(items) = &self.items {
+                    for (i, item) in list.iter().enumerate() {
+                        items.check(item, &join(path, &i.to_string()), errors);
+                    }
+                }
+            }
+            ValueKind::Table(table) => self.check_table(table, value.loc(), path, errors),
+            _ => {}
+        }
+    }
+
+    fn check_table(
+        &self,
+        table: &Table,
+        loc: Option<&Location>,
+        path: &str,
+        errors: &mut Vec<Error>,
+    ) {
+        for (key, schema) in &self.fields {
+            match table.get(key) {
+                Some(value) => schema.check(value, &join(path, key), errors),
+                None if !schema.optional => errors.push(violation(
+                    format!("missing required key '{}'", join(path, key)),
+                    loc,
+                )),
+                None => {}
+            }
+        }
+
+        for entry in table.entries() {
+            if self.fields.iter().any(|(key, _)| key == entry.key()) {
+                continue;
+            }
+
+            let path = join(path, entry.key());
+
+            if self.deny_unknown {
+                errors.push(violation(
+                    format!("unknown key '{}'", path),
+                    entry.key_loc().or(entry.value().loc()),
+                ));
+            } else if let Some(values) = &self.values {
+                values.check(entry.value(), &path, errors);
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Schema {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Schema> {
+        Schema::parse(s.as_bytes())
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn unit(value: &Value, n: usize) -> &'static str {
+    match (value.kind(), n) {
+        (ValueKind::String(..), 1) => "character",
+        (ValueKind::String(..), _) => "characters",
+        (ValueKind::Table(_), 1) => "key",
+        (ValueKind::Table(_), _) => "keys",
+        (_, 1) => "item",
+        _ => "items",
+    }
+}
+
+fn describe(value: &Value) -> String {
+    match value.as_str() {
+        Some(s) => format!("{:?}", s),
+        None => serde_json::Value::from(value.clone()).to_string(),
+    }
+}
+
+fn violation(desc: String, loc: Option<&Location>) -> Error {
+    Error {
+        kind: ErrorKind::SchemaViolation,
+        desc,
+        loc: loc.copied(),
+        file: None,
+    }
+}
+
+fn invalid(desc: impl Into<String>, value: &Value) -> Error {
+    Error {
+        kind: ErrorKind::InvalidSchema,
+        desc: desc.into(),
+        loc: value.loc().copied(),
+        file: None,
+    }
+}
+
+fn fields(table: &Table) -> Result<Vec<(String, Schema)>> {
+    table
+        .iter()
+        .map(|(key, value)| Ok((key.to_string(), schema(value)?)))
+        .collect()
+}
+
+/// Reads the schema of a single value, a type name or a table of
+/// constraints.
+fn schema(value: &Value) -> Result<Schema> {
+    if let Some(name) = value.as_str() {
+        return Ok(Schema::of(type_name(value, name)?));
+    }
+
+    let table = value
+        .as_table()
+        .ok_or_else(|| invalid("expected a type name or a table of constraints", value))?;
+
+    let mut schema = Schema::new();
+
+    for entry in table.entries() {
+        let value = entry.value();
+
+        match entry.key() {
+            "type" => {
+                schema.types = match value.kind() {
+                    ValueKind::List(names) => names
+                        .iter()
+                        .map(|v| type_name(v, string(v)?))
+                        .collect::<Result<_>>()?,
+                    _ => vec![type_name(value, string(value)?)?],
+                }
+            }
+            "optional" => schema.optional = boolean(value)?,
+            "description" => schema.description = Some(string(value)?.to_string()),
+            "min" => schema.min = Some(number(value)?),
+            "max" => schema.max = Some(number(value)?),
+            "min_len" => schema.min_len = Some(length(value)?),
+            "max_len" => schema.max_len = Some(length(value)?),
+            "one_of" => {
+                schema.one_of = value
+                    .as_list()
+                    .ok_or_else(|| invalid("'one_of' must be a list", value))?
+                    .clone()
+            }
+            "pattern" => {
+                let pattern = Regex::new(string(value)?)
+                    .map_err(|e| invalid(format!("invalid pattern: {}", e), value))?;
+
+                schema.pattern = Some(pattern);
+            }
+            "items" => schema.items = Some(Box::new(self::schema(value)?)),
+            "fields" => {
+                let table = value
+                    .as_table()
+                    .ok_or_else(|| invalid("'fields' must be a table", value))?;
+
+                schema.fields = fields(table)?;
+            }
+            "values" => schema.values = Some(Box::new(self::schema(value)?)),
+            "deny_unknown" => schema.deny_unknown = boolean(value)?,
+            key => {
+                return Err(Error {
+                    kind: ErrorKind::InvalidSchema,
+                    desc: format!("unknown constraint '{}'", key),
+                    loc: entry.key_loc().copied(),
+                    file: None,
+                })
+            }
+        }
+    }
+
+    Ok(schema)
+}
+
+fn type_name(value: &Value, name: &str) -> Result<Type> {
+    Type::from_name(name).ok_or_else(|| {
+        invalid(
+            format!(
+                "unknown type '{}', expected null, bool, integer, number, string, table or list",
+                name
+            ),
+            value,
+        )
+    })
+}
+
+fn string(value: &Value) -> Result<&str> {
+    value
+        .as_str()
+        .ok_or_else(|| invalid("expected a string", value))
+}
+
+fn boolean(value: &Value) -> Result<bool> {
+    value
+        .as_bool()
+        .ok_or_else(|| invalid("expected true or false", value))
+}
+
+fn number(value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| invalid("expected a number", value))
+}
+
+fn length(value: &Value) -> Result<usize> {
+    value
+        .as_u64()
+        .map(|n| n as usize)
+        .ok_or_else(|| invalid("expected a non-negative integer", value))
+}
@@ -0,0 +1,747 @@
+use crate::prelude::*;
+
+use serde::ser;
+use serde::Serialize;
+
+use crate::lexer::is_identifier;
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error::new(ErrorKind::Custom, msg.to_string())
+    }
+}
+
+fn io_error(e: std::io::Error) -> Error {
+    Error::new(ErrorKind::Io, e.to_string())
+}
+
+/// Escapes `value` as a double quoted string, or as a template string when
+/// it spans several lines.
+pub fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+
+    let (delimiter, escaped) = if value.contains('\n') {
+        ('`', '`')
+    } else {
+        ('"', '"')
+    };
+
+    out.push(delimiter);
+
+    let mut chars = value.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // template strings would read "${" as a placeholder
+        let placeholder = delimiter == '`' && c == '$' && chars.peek() == Some(&'{');
+
+        if c == '\\' || c == escaped || placeholder {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out.push(delimiter);
+
+    out
+}
+
+/// Writes `key` bare when it is a valid identifier, quoted otherwise.
+pub fn quote_key(key: &str) -> String {
+    if is_identifier(key) {
+        key.to_string()
+    } else {
+        quote(key)
+    }
+}
+
+/// Serializes Rust values into MCL text.
+///
+/// A whole document has to be a table or a list, so a scalar is an error
+/// unless written with [`Serializer::braced`] as part of another document.
+pub struct Serializer<W> {
+    writer: W,
+    pretty: bool,
+    depth: usize,
+    root: bool,
+}
+
+impl<W: std::io::Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Serializer {
+            writer,
+            pretty: false,
+            depth: 0,
+            root: true,
+        }
+    }
+
+    /// Serializer writing one entry per line, indented by four spaces.
+    pub fn pretty(writer: W) -> Self {
+        Serializer {
+            pretty: true,
+            ..Serializer::new(writer)
+        }
+    }
+
+    /// Writes tables with braces even at the top level, as needed for a
+    /// value placed inside an existing document.
+    pub fn braced(mut self) -> Self {
+        self.root = false;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        self.writer.write_all(s.as_bytes()).map_err(io_error)
+    }
+
+    /// Writes a scalar, which cannot be a whole document as it would read
+    /// back as a list of one item.
+    fn scalar(&mut self, s: &str) -> Result<()> {
+        if self.root {
+            return Err(Error::new(
+                ErrorKind::InvalidType,
+                format!(
+                    "cannot write '{}' as a whole document, only a table or a list can be",
+                    s
+                ),
+            ));
+        }
+
+        self.write(s)
+    }
+
+    fn open(&mut self, kind: Kind) -> Result<Compound<'_, W>> {
+        match kind {
+            Kind::Table => self.write("{")?,
+            Kind::List => self.write("[")?,
+            Kind::Root => {}
+        }
+
+        if kind != Kind::Root {
+            self.depth += 1;
+        }
+
+        Ok(Compound {
+            ser: self,
+            kind,
+            empty: true,
+            variant: false,
+        })
+    }
+
+    /// Opens a table, or starts writing bare top level entries when this is
+    /// the whole document.
+    fn open_table(&mut self) -> Result<Compound<'_, W>> {
+        if std::mem::replace(&mut self.root, false) {
+            Ok(Compound {
+                ser: self,
+                kind: Kind::Root,
+                empty: true,
+                variant: false,
+            })
+        } else {
+            self.open(Kind::Table)
+        }
+    }
+
+    /// Opens the single entry table holding an enum variant.
+    fn open_variant(&mut self, variant: &str) -> Result<Compound<'_, W>> {
+        self.root = false;
+
+        let mut compound = self.open(Kind::Table)?;
+
+        compound.item()?;
+        compound.ser.write(&quote_key(variant))?;
+        compound.ser.write(" ")?;
+
+        Ok(compound)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Table,
+    List,
+    Root,
+}
+
+pub struct Compound<'a, W> {
+    ser: &'a mut Serializer<W>,
+    kind: Kind,
+    empty: bool,
+    // also closes the variant table wrapping it
+    variant: bool,
+}
+
+impl<W: std::io::Write> Compound<'_, W> {
+    /// Writes the separator in front of the next entry or element.
+    fn item(&mut self) -> Result<()> {
+        let first = std::mem::replace(&mut self.empty, false);
+
+        if self.ser.pretty && self.kind != Kind::Root {
+            self.ser.write("\n")?;
+            self.ser.write(&"    ".repeat(self.ser.depth))
+        } else if self.ser.pretty && !first {
+            self.ser.write("\n")
+        } else if !first || self.kind == Kind::Table {
+            self.ser.write(" ")
+        } else {
+            Ok(())
+        }
+    }
+
+    fn key(&mut self, key: &str) -> Result<()> {
+        // a quoted first key would make the document read as a list
+        if self.kind == Kind::Root && self.empty && !is_identifier(key) {
+            self.ser.depth += 1;
+            self.ser.write("{")?;
+            self.kind = Kind::Table;
+        }
+
+        self.item()?;
+        self.ser.write(&quote_key(key))?;
+        self.ser.write(" ")
+    }
+
+    /// Replaces the variant table by a list or table nested in it.
+    fn into_variant(self, kind: Kind) -> Result<Self> {
+        let mut compound = self.ser.open(kind)?;
+
+        compound.variant = true;
+
+        Ok(compound)
+    }
+
+    fn close(self) -> Result<()> {
+        if self.kind == Kind::Root {
+            // an empty document would not parse, an empty table does
+            return match self.empty {
+                true => self.ser.write("{}"),
+                false => Ok(()),
+            };
+        }
+
+        close(self.ser, self.kind, self.empty)?;
+
+        if self.variant {
+            close(self.ser, Kind::Table, false)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn close<W: std::io::Write>(ser: &mut Serializer<W>, kind: Kind, empty: bool) -> Result<()> {
+    ser.depth -= 1;
+
+    if !empty {
+        if ser.pretty {
+            ser.write("\n")?;
+            ser.write(&"    ".repeat(ser.depth))?;
+        } else if kind == Kind::Table {
+            ser.write(" ")?;
+        }
+    }
+
+    match kind {
+        Kind::List => ser.write("]"),
+        _ => ser.write("}"),
+    }
+}
+
+impl<'a, W: std::io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Compound<'a, W>;
+    type SerializeTuple = Compound<'a, W>;
+    type SerializeTupleStruct = Compound<'a, W>;
+    type SerializeTupleVariant = Compound<'a, W>;
+    type SerializeMap = Compound<'a, W>;
+    type SerializeStruct = Compound<'a, W>;
+    type SerializeStructVariant = Compound<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.scalar(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v as u64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.scalar(&v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        if !v.is_finite() {
+            return Err(Error::new(
+                ErrorKind::InvalidValue,
+                format!("{} cannot be written in MCL", v),
+            ));
+        }
+
+        let mut text = v.to_string();
+
+        // keep it a float when read back
+        if !text.contains('.') {
+            text.push_str(".0");
+        }
+
+        self.scalar(&text)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.scalar(&quote(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = self.serialize_seq(Some(v.len()))?;
+
+        for b in v {
+            seq.serialize_element(b)?;
+        }
+
+        seq.end()
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.scalar("null")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let compound = self.open_variant(variant)?;
+
+        value.serialize(&mut *compound.ser)?;
+
+        compound.close()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.root = false;
+        self.open(Kind::List)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Compound<'a, W>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.open_variant(variant)?.into_variant(Kind::List)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, W>> {
+        self.open_table()
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Compound<'a, W>> {
+        self.open_table()
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Compound<'a, W>> {
+        self.open_variant(variant)?.into_variant(Kind::Table)
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeSeq for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        self.item()?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeTuple for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeTupleStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeMap for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        let key = key.serialize(KeySerializer)?;
+
+        self.key(&key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeStruct for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.key(key)?;
+        value.serialize(&mut *self.ser)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeTupleVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+impl<W: std::io::Write> ser::SerializeStructVariant for Compound<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.close()
+    }
+}
+
+/// Turns map keys into strings, accepting the scalar types serde_json does.
+struct KeySerializer;
+
+fn key_must_be_a_string() -> Error {
+    Error::new(ErrorKind::InvalidType, "table keys must be strings")
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+
+    type SerializeSeq = ser::Impossible<String, Error>;
+    type SerializeTuple = ser::Impossible<String, Error>;
+    type SerializeTupleStruct = ser::Impossible<String, Error>;
+    type SerializeTupleVariant = ser::Impossible<String, Error>;
+    type SerializeMap = ser::Impossible<String, Error>;
+    type SerializeStruct = ser::Impossible<String, Error>;
+    type SerializeStructVariant = ser::Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_must_be_a_string())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_must_be_a_string())
+    }
+}
+
+/// Serializes `value` as MCL into `writer`.
+pub fn to_writer<W: std::io::Write, T: ?Sized + Serialize>(writer: W, value: &T) -> Result<()> {
+    let mut serializer = Serializer::new(writer);
+
+    value.serialize(&mut serializer)
+}
+
+/// Serializes `value` as MCL into `writer`, one entry per line.
+pub fn to_writer_pretty<W: std::io::Write, T: ?Sized + Serialize>(
+    writer: W,
+    value: &T,
+) -> Result<()> {
+    let mut serializer = Serializer::pretty(writer);
+
+    value.serialize(&mut serializer)
+}
+
+/// Serializes `value` as a compact, single line MCL string.
+pub fn to_string<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    let mut output = Vec::new();
+
+    to_writer(&mut output, value)?;
+
+    Ok(String::from_utf8(output).expect("serializer only writes valid UTF-8"))
+}
+
+/// Serializes `value` as an indented MCL string, one entry per line.
+pub fn to_string_pretty<T: ?Sized + Serialize>(value: &T) -> Result<String> {
+    let mut output = Vec::new();
+
+    to_writer_pretty(&mut output, value)?;
+
+    Ok(String::from_utf8(output).expect("serializer only writes valid UTF-8"))
+}
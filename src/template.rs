@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use crate::prelude::*;
+
+use crate::env::EnvResolver;
+use crate::parser::bytes_to_str;
+use crate::parser::create_string;
+use crate::reference::segments;
+use crate::token::Location;
+use crate::token::Position;
+use crate::token::Token;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+#[derive(Debug, Clone)]
+pub enum Part {
+    Text(String),
+    /// `${path}`, located at the whole placeholder.
+    Placeholder(String, Location),
+    /// `${env:NAME}` or `${env:NAME:-default}`.
+    Env {
+        name: String,
+        default: Option<String>,
+        loc: Location,
+    },
+}
+
+/// A template string waiting for its placeholders to be filled in.
+#[derive(Debug, Clone)]
+pub struct Template {
+    /// Keys and list indices leading to the string in the document.
+    pub path: Vec<String>,
+    pub parts: Vec<Part>,
+    /// File the template was written in, when parsing files.
+    pub file: Option<String>,
+}
+
+/// Whether `raw` template string content holds an unescaped `${`.
+pub fn has_placeholder(raw: &[u8]) -> bool {
+    let mut i = 0;
+
+    while i < raw.len() {
+        match raw[i] {
+            b'\\' => i += 2,
+            b'$' if raw.get(i + 1) == Some(&b'{') => return true,
+            _ => i += 1,
+        }
+    }
+
+    false
+}
+
+/// Splits the content of a template string token into text and
+/// placeholders, unescaping the text.
+pub fn parts(token: &Token) -> Result<Vec<Part>> {
+    let raw = token.bytes();
+    let start = token.loc().start();
+
+    // the content starts right after the opening backtick
+    let mut line = start.line();
+    let mut column = start.column() + 1;
+    let base = start.index() + 1;
+
+    let mut parts = Vec::new();
+    let mut text = 0;
+    let mut i = 0;
+
+    let position = |i: usize, line: usize, column: usize| Position::new(line, column, base + i);
+
+    while i < raw.len() {
+        match raw[i] {
+            b'\\' if raw.get(i + 1) == Some(&b'\n') => {
+                i += 2;
+                line += 1;
+                column = 1;
+            }
+            b'\\' => {
+                i += 2;
+                column += 2;
+            }
+            b'\n' => {
+                i += 1;
+                line += 1;
+                column = 1;
+            }
+            b'$' if raw.get(i + 1) == Some(&b'{') => {
+                let open = position(i, line, column);
+
+                let close = raw[i..].iter().position(|&b| b == b'}' || b == b'\n');
+
+                let end = match close {
+                    Some(n) if raw[i + n] == b'}' => i + n,
+                    _ => {
+                        return Err(Error::at(
+                            ErrorKind::InvalidPlaceholder,
+                            "unterminated placeholder",
+                            Location::new(open, position(i + 2, line, column + 2)),
+                        ))
+                    }
+                };
+
+                let loc = Location::new(open, position(end + 1, line, column + end + 1 - i));
+                let path = bytes_to_str(&raw[i + 2..end]).trim();
+
+                if path.is_empty() {
+                    return Err(Error::at(
+                        ErrorKind::InvalidPlaceholder,
+                        "empty placeholder",
+                        loc,
+                    ));
+                }
+
+                if text < i {
+                    parts.push(Part::Text(create_string(&raw[text..i])));
+                }
+
+                parts.push(match path.strip_prefix("env:") {
+                    Some(env) => {
+                        let (name, default) = match env.split_once(":-") {
+                            Some((name, default)) => (name, Some(default.to_string())),
+                            None => (env, None),
+                        };
+
+                        if name.is_empty() {
+                            return Err(Error::at(
+                                ErrorKind::InvalidPlaceholder,
+                                "missing environment variable name",
+                                loc,
+                            ));
+                        }
+
+                        Part::Env {
+                            name: name.to_string(),
+                            default,
+                            loc,
+                        }
+                    }
+                    None => Part::Placeholder(path.to_string(), loc),
+                });
+
+                column += end + 1 - i;
+                i = end + 1;
+                text = i;
+            }
+            _ => {
+                i += 1;
+                column += 1;
+            }
+        }
+    }
+
+    if text < raw.len() {
+        parts.push(Part::Text(create_string(&raw[text..])));
+    }
+
+    Ok(parts)
+}
+
+/// Text a referenced value is replaced with.
+fn scalar(value: &Value, path: &str, loc: Location) -> Result<String> {
+    match value.kind() {
+        ValueKind::String(s, _) => Ok(s.clone()),
+        ValueKind::Number(n) => Ok(match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => i.to_string(),
+            (_, Some(u)) => u.to_string(),
+            _ => n.as_str().replace('_', ""),
+        }),
+        ValueKind::Bool(b) => Ok(b.to_string()),
+        ValueKind::Null => Ok("null".to_string()),
+        _ => Err(Error::at(
+            ErrorKind::InvalidType,
+            format!(
+                "cannot interpolate '{}', it is a {}",
+                path,
+                value.type_name()
+            ),
+            loc,
+        )),
+    }
+}
+
+struct Interpolator<'a> {
+    templates: &'a [Template],
+    index: HashMap<&'a [String], usize>,
+    resolved: Vec<Option<String>>,
+    root: &'a Value,
+    variables: &'a Value,
+    env: Option<&'a dyn EnvResolver>,
+    stack: Vec<usize>,
+}
+
+impl Interpolator<'_> {
+    fn resolve(&mut self, i: usize) -> Result<String> {
+        if let Some(text) = &self.resolved[i] {
+            return Ok(text.clone());
+        }
+
+        let templates = self.templates;
+
+        self.fill(i)
+            .map_err(|e| e.in_file(templates[i].file.as_deref()))
+    }
+
+    /// Resolves every part of the template `i`.
+    fn fill(&mut self, i: usize) -> Result<String> {
+        self.stack.push(i);
+
+        let mut text = String::new();
+
+        let templates = self.templates;
+
+        for part in &templates[i].parts {
+            match part {
+                Part::Text(t) => text.push_str(t),
+                Part::Env { name, default, loc } => {
+                    let value = match self.env {
+                        Some(env) => env
+                            .get(name)
+                            // like `:-` in a shell, the default also replaces an empty value
+                            .filter(|v| default.is_none() || !v.is_empty())
+                            .or_else(|| default.clone())
+                            .ok_or_else(|| {
+                                Error::at(
+                                    ErrorKind::MissingEnvVar,
+                                    format!("environment variable '{}' is not set", name),
+                                    *loc,
+                                )
+                            }),
+                        None => Err(Error::at(
+                            ErrorKind::UnresolvedReference,
+                            format!(
+                                "cannot resolve '${{env:{}}}', environment variables are not enabled",
+                                name
+                            ),
+                            *loc,
+                        )),
+                    };
+
+                    match value {
+                        Ok(value) => text.push_str(&value),
+                        Err(e) => {
+                            self.stack.pop();
+                            return Err(e);
+                        }
+                    }
+                }
+                Part::Placeholder(path, loc) => {
+                    let value = match self.index.get(segments(path).as_slice()) {
+                        Some(&j) if self.stack.contains(&j) => {
+                            let mut cycle: Vec<String> = self.stack
+                                [self.stack.iter().position(|&k| k == j).unwrap_or(0)..]
+                                .iter()
+                                .map(|&k| templates[k].path.join("."))
+                                .collect();
+
+                            cycle.push(path.clone());
+
+                            Err(Error::at(
+                                ErrorKind::ReferenceCycle,
+                                format!("cyclic reference: {}", cycle.join(" -> ")),
+                                *loc,
+                            ))
+                        }
+                        Some(&j) => self.resolve(j),
+                        None => match self
+                            .root
+                            .pointer(path)
+                            .or_else(|| self.variables.pointer(path))
+                        {
+                            Some(value) => scalar(value, path, *loc),
+                            None => Err(Error::at(
+                                ErrorKind::UnresolvedReference,
+                                format!("cannot resolve '${{{}}}'", path),
+                                *loc,
+                            )),
+                        },
+                    };
+
+                    match value {
+                        Ok(value) => text.push_str(&value),
+                        Err(e) => {
+                            self.stack.pop();
+                            return Err(e);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.stack.pop();
+        self.resolved[i] = Some(text.clone());
+
+        Ok(text)
+    }
+}
+
+/// Fills in the placeholders of `templates`, looking paths up in `root`
+/// first and in `variables` second, and environment variables up in `env`.
+///
+/// Returns every error encountered.
+pub fn interpolate(
+    root: &mut Value,
+    templates: &[Template],
+    variables: &Value,
+    env: Option<&dyn EnvResolver>,
+) -> Vec<Error> {
+    let mut errors: Vec<Error> = Vec::new();
+
+    let resolved = {
+        let mut interpolator = Interpolator {
+            templates,
+            index: templates
+                .iter()
+                .enumerate()
+                .map(|(i, t)| (t.path.as_slice(), i))
+                .collect(),
+            resolved: vec![None; templates.len()],
+            root,
+            variables,
+            env,
+            stack: Vec::new(),
+        };
+
+        for i in 0..templates.len() {
+            if let Err(e) = interpolator.resolve(i) {
+                // a broken template is reported once, not by every user
+                if !errors.iter().any(|x| x.loc == e.loc) {
+                    errors.push(e);
+                }
+            }
+        }
+
+        interpolator.resolved
+    };
+
+    for (template, text) in templates.iter().zip(resolved) {
+        if let (Some(text), Some(value)) = (text, root.lookup_mut(&template.path)) {
+            if let ValueKind::String(s, _) = value.kind_mut() {
+                *s = text;
+            }
+        }
+    }
+
+    errors
+}
@@ -1,10 +1,11 @@
 #[derive(Debug)]
 pub enum LiteralKind<'a> {
     String(Token<'a>),
+    TemplateString(Token<'a>),
     Number(Token<'a>),
-    True,
-    False,
-    Null,
+    True(Token<'a>),
+    False(Token<'a>),
+    Null(Token<'a>),
 }
 
 #[derive(Debug)]
@@ -13,21 +14,118 @@ pub enum IdentifierKind<'a> {
 }
 
 #[derive(Debug)]
-pub enum DelimiterKind {
-    TableTerm,
-    TablePrec,
-    ListPrec,
-    ListTerm,
+pub enum DelimiterKind<'a> {
+    TableTerm(Token<'a>),
+    TablePrec(Token<'a>),
+    ListPrec(Token<'a>),
+    ListTerm(Token<'a>),
+}
+
+/// Ways of reusing a value defined elsewhere, the tokens holding their sigil.
+#[derive(Debug)]
+pub enum ReferenceKind<'a> {
+    /// `&name`, naming the value that follows.
+    Anchor(Token<'a>),
+    /// `*name`, a copy of the value anchored as `&name`.
+    Alias(Token<'a>),
+    /// `$ref path.to.key`, a copy of the value at that path.
+    Ref(Token<'a>),
+    /// `extends path.to.table`, the table that follows being merged over a
+    /// copy of that one.
+    Extends(Token<'a>),
+}
+
+/// Source text between tokens, kept so tools can reproduce the input.
+#[derive(Debug)]
+pub enum TriviaKind<'a> {
+    /// Run of spaces, tabs and carriage returns.
+    Whitespace(Token<'a>),
+    Newline(Token<'a>),
+    /// `// ...` up to, but not including, the end of the line.
+    LineComment(Token<'a>),
+    /// `/* ... */`, possibly spanning several lines.
+    BlockComment(Token<'a>),
 }
 
 #[derive(Debug)]
 pub enum TokenKind<'a> {
     Identifier(IdentifierKind<'a>),
     Literal(LiteralKind<'a>),
-    Delimiter(DelimiterKind),
+    Delimiter(DelimiterKind<'a>),
+    /// `@name`, the token holding the leading `@` too.
+    Directive(Token<'a>),
+    Reference(ReferenceKind<'a>),
+    Trivia(TriviaKind<'a>),
 }
 
-#[derive(Debug)]
+impl<'a> TokenKind<'a> {
+    #[inline(always)]
+    pub fn token(&self) -> &Token<'a> {
+        match self {
+            TokenKind::Identifier(IdentifierKind::String(t)) => t,
+
+            TokenKind::Literal(LiteralKind::String(t))
+            | TokenKind::Literal(LiteralKind::TemplateString(t))
+            | TokenKind::Literal(LiteralKind::Number(t))
+            | TokenKind::Literal(LiteralKind::True(t))
+            | TokenKind::Literal(LiteralKind::False(t))
+            | TokenKind::Literal(LiteralKind::Null(t)) => t,
+
+            TokenKind::Delimiter(DelimiterKind::TableTerm(t))
+            | TokenKind::Delimiter(DelimiterKind::TablePrec(t))
+            | TokenKind::Delimiter(DelimiterKind::ListPrec(t))
+            | TokenKind::Delimiter(DelimiterKind::ListTerm(t)) => t,
+
+            TokenKind::Directive(t) => t,
+
+            TokenKind::Reference(ReferenceKind::Anchor(t))
+            | TokenKind::Reference(ReferenceKind::Alias(t))
+            | TokenKind::Reference(ReferenceKind::Ref(t))
+            | TokenKind::Reference(ReferenceKind::Extends(t)) => t,
+
+            TokenKind::Trivia(TriviaKind::Whitespace(t))
+            | TokenKind::Trivia(TriviaKind::Newline(t))
+            | TokenKind::Trivia(TriviaKind::LineComment(t))
+            | TokenKind::Trivia(TriviaKind::BlockComment(t)) => t,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, TokenKind::Trivia(_))
+    }
+
+    #[inline(always)]
+    pub fn loc(&self) -> &Location {
+        self.token().loc()
+    }
+}
+
+impl std::fmt::Display for TokenKind<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let text = String::from_utf8_lossy(self.token().bytes());
+
+        match self {
+            TokenKind::Identifier(_) => write!(f, "identifier '{}'", text),
+            TokenKind::Literal(LiteralKind::String(_)) => write!(f, "string \"{}\"", text),
+            TokenKind::Literal(LiteralKind::TemplateString(_)) => {
+                write!(f, "template string `{}`", text)
+            }
+            TokenKind::Literal(LiteralKind::Number(_)) => write!(f, "number '{}'", text),
+            TokenKind::Directive(_) => write!(f, "directive '{}'", text),
+            TokenKind::Reference(ReferenceKind::Anchor(_)) => write!(f, "anchor '{}'", text),
+            TokenKind::Reference(ReferenceKind::Alias(_)) => write!(f, "alias '{}'", text),
+            TokenKind::Reference(ReferenceKind::Ref(_)) => write!(f, "reference '{}'", text),
+            TokenKind::Reference(ReferenceKind::Extends(_)) => write!(f, "'{}'", text),
+            TokenKind::Trivia(TriviaKind::Whitespace(_)) => write!(f, "whitespace"),
+            TokenKind::Trivia(TriviaKind::Newline(_)) => write!(f, "newline"),
+            TokenKind::Trivia(_) => write!(f, "comment"),
+            TokenKind::Literal(_) | TokenKind::Delimiter(_) => write!(f, "'{}'", text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Position {
     l: usize,
     c: usize,
@@ -56,7 +154,17 @@ impl<'a> Position {
     }
 }
 
-#[derive(Debug)]
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}:{}", self.l, self.c)
+    }
+}
+
+/// Span of a token in the source, `start` inclusive and `end` exclusive.
+///
+/// For strings the span covers the surrounding quotes or backticks, while
+/// [`Token::bytes`] only holds the content between them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Location {
     s: Position,
     e: Position,
@@ -79,6 +187,12 @@ impl<'a> Location {
     }
 }
 
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.s)
+    }
+}
+
 #[derive(Debug)]
 pub struct Token<'a> {
     l: Location,
@@ -92,12 +206,12 @@ impl<'a> Token<'a> {
     }
 
     #[inline(always)]
-    pub fn bytes(&'a self) -> &'a [u8] {
+    pub fn bytes(&self) -> &'a [u8] {
         self.b
     }
 
     #[inline(always)]
-    pub fn loc(&'a self) -> &'a Location {
+    pub fn loc(&self) -> &Location {
         &self.l
     }
 }
@@ -0,0 +1,683 @@
+use crate::token::Location;
+
+/// How a string was written in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringKind {
+    /// `"double quoted"`
+    Normal,
+    /// `` `backtick quoted` ``
+    Template,
+}
+
+/// A number kept exactly as it was written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Number {
+    text: String,
+}
+
+impl Number {
+    /// Wraps `text` as is, callers are expected to pass valid number text.
+    pub fn new(text: impl Into<String>) -> Number {
+        Number { text: text.into() }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.as_i64().is_some() || self.as_u64().is_some()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.radix() {
+            Some(n) => n.try_into().ok(),
+            None => self.digits().parse().ok(),
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.radix() {
+            Some(n) => n.try_into().ok(),
+            None => self.digits().parse().ok(),
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.radix() {
+            Some(n) => Some(n as f64),
+            None => self.digits().parse().ok(),
+        }
+    }
+
+    /// Text without its `_` separators.
+    fn digits(&self) -> String {
+        self.text.replace('_', "")
+    }
+
+    /// Value of a `0x`, `0o` or `0b` integer, `None` for other numbers.
+    fn radix(&self) -> Option<i128> {
+        let text = self.digits();
+
+        let (negative, digits) = match text.as_bytes().first() {
+            Some(b'-') => (true, &text[1..]),
+            Some(b'+') => (false, &text[1..]),
+            _ => (false, text.as_str()),
+        };
+
+        let radix = match digits.get(..2) {
+            Some("0x") => 16,
+            Some("0o") => 8,
+            Some("0b") => 2,
+            _ => return None,
+        };
+
+        let n = i128::from_str_radix(&digits[2..], radix).ok()?;
+
+        Some(if negative { -n } else { n })
+    }
+}
+
+impl std::fmt::Display for Number {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+macro_rules! number_from {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Number {
+                fn from(n: $ty) -> Number {
+                    Number::new(n.to_string())
+                }
+            }
+        )*
+    };
+}
+
+number_from! { i8 i16 i32 i64 u8 u16 u32 u64 }
+
+impl From<f64> for Number {
+    fn from(n: f64) -> Number {
+        let mut text = n.to_string();
+
+        if n.is_finite() && !text.contains('.') {
+            text.push_str(".0");
+        }
+
+        Number::new(text)
+    }
+}
+
+pub type List = Vec<Value>;
+
+/// A key and its value, along with where the key was written.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    key: String,
+    loc: Option<Location>,
+    value: Value,
+}
+
+impl Entry {
+    pub fn new(key: impl Into<String>, loc: Option<Location>, value: Value) -> Entry {
+        Entry {
+            key: key.into(),
+            loc,
+            value,
+        }
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Location of the key, `None` for entries that were not parsed.
+    pub fn key_loc(&self) -> Option<&Location> {
+        self.loc.as_ref()
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut Value {
+        &mut self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+}
+
+/// Table keeping its entries in insertion order.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    entries: Vec<Entry>,
+}
+
+impl Table {
+    pub fn new() -> Table {
+        Table::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn position(&self, key: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.key == key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.position(key).is_some()
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entry(key).map(|e| &e.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.entry_mut(key).map(|e| &mut e.value)
+    }
+
+    pub fn entry(&self, key: &str) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.key == key)
+    }
+
+    pub fn entry_mut(&mut self, key: &str) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|e| e.key == key)
+    }
+
+    /// Inserts `value` under `key`, replacing an existing value in place
+    /// so the key keeps its original position.
+    pub fn insert(&mut self, key: impl Into<String>, value: Value) -> Option<Value> {
+        self.insert_entry(Entry::new(key, None, value))
+    }
+
+    /// Like [`Table::insert`], but also replaces the key location.
+    pub fn insert_entry(&mut self, entry: Entry) -> Option<Value> {
+        match self.position(&entry.key) {
+            Some(i) => Some(std::mem::replace(&mut self.entries[i], entry).value),
+            None => {
+                self.entries.push(entry);
+                None
+            }
+        }
+    }
+
+    /// Removes `key`, shifting the following entries to keep their order.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        self.position(key).map(|i| self.entries.remove(i).value)
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.entries.iter().map(|e| (e.key.as_str(), &e.value))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut Value)> {
+        self.entries
+            .iter_mut()
+            .map(|e| (e.key.as_str(), &mut e.value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.key.as_str())
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|e| &e.value)
+    }
+}
+
+/// Tables compare equal when they hold the same entries, in any order.
+impl PartialEq for Table {
+    fn eq(&self, other: &Table) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl FromIterator<(String, Value)> for Table {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Table {
+        let mut table = Table::new();
+
+        for (key, value) in iter {
+            table.insert(key, value);
+        }
+
+        table
+    }
+}
+
+impl IntoIterator for Table {
+    type Item = Entry;
+    type IntoIter = std::vec::IntoIter<Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValueKind {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(String, StringKind),
+    Table(Table),
+    List(List),
+}
+
+/// A parsed MCL value together with the location it was parsed from.
+///
+/// Equality only looks at the contents, not at locations.
+#[derive(Debug, Clone)]
+pub struct Value {
+    kind: ValueKind,
+    loc: Option<Location>,
+}
+
+static NULL: Value = Value {
+    kind: ValueKind::Null,
+    loc: None,
+};
+
+impl Value {
+    pub fn new(kind: ValueKind) -> Value {
+        Value { kind, loc: None }
+    }
+
+    pub fn with_loc(kind: ValueKind, loc: Location) -> Value {
+        Value {
+            kind,
+            loc: Some(loc),
+        }
+    }
+
+    pub fn kind(&self) -> &ValueKind {
+        &self.kind
+    }
+
+    pub fn kind_mut(&mut self) -> &mut ValueKind {
+        &mut self.kind
+    }
+
+    pub fn into_kind(self) -> ValueKind {
+        self.kind
+    }
+
+    /// Location the value was parsed from, `None` for values built in code.
+    pub fn loc(&self) -> Option<&Location> {
+        self.loc.as_ref()
+    }
+
+    pub fn set_loc(&mut self, loc: Option<Location>) {
+        self.loc = loc;
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.kind, ValueKind::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.kind {
+            ValueKind::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match &self.kind {
+            ValueKind::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.kind {
+            ValueKind::String(s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn string_kind(&self) -> Option<StringKind> {
+        match &self.kind {
+            ValueKind::String(_, kind) => Some(*kind),
+            _ => None,
+        }
+    }
+
+    pub fn as_table(&self) -> Option<&Table> {
+        match &self.kind {
+            ValueKind::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_table_mut(&mut self) -> Option<&mut Table> {
+        match &mut self.kind {
+            ValueKind::Table(t) => Some(t),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&List> {
+        match &self.kind {
+            ValueKind::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    pub fn as_list_mut(&mut self) -> Option<&mut List> {
+        match &mut self.kind {
+            ValueKind::List(l) => Some(l),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` if this is a table.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.as_table().and_then(|t| t.get(key))
+    }
+
+    /// Follows a dot separated path such as `server.hosts.0`, where numeric
+    /// segments index into lists.
+    pub fn pointer(&self, path: &str) -> Option<&Value> {
+        path.split('.')
+            .try_fold(self, |value, segment| match &value.kind {
+                ValueKind::Table(t) => t.get(segment),
+                ValueKind::List(l) => segment.parse::<usize>().ok().and_then(|i| l.get(i)),
+                _ => None,
+            })
+    }
+
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut Value> {
+        path.split('.')
+            .try_fold(self, |value, segment| match &mut value.kind {
+                ValueKind::Table(t) => t.get_mut(segment),
+                ValueKind::List(l) => segment.parse::<usize>().ok().and_then(|i| l.get_mut(i)),
+                _ => None,
+            })
+    }
+
+    /// Follows the exact keys and list indices of `path`, which may hold
+    /// dots of their own.
+    pub(crate) fn lookup(&self, path: &[String]) -> Option<&Value> {
+        path.iter()
+            .try_fold(self, |value, segment| match &value.kind {
+                ValueKind::Table(t) => t.get(segment),
+                ValueKind::List(l) => segment.parse::<usize>().ok().and_then(|i| l.get(i)),
+                _ => None,
+            })
+    }
+
+    pub(crate) fn lookup_mut(&mut self, path: &[String]) -> Option<&mut Value> {
+        path.iter()
+            .try_fold(self, |value, segment| match &mut value.kind {
+                ValueKind::Table(t) => t.get_mut(segment),
+                ValueKind::List(l) => segment.parse::<usize>().ok().and_then(|i| l.get_mut(i)),
+                _ => None,
+            })
+    }
+
+    /// Name of the value's type, as used in error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self.kind {
+            ValueKind::Null => "null",
+            ValueKind::Bool(_) => "boolean",
+            ValueKind::Number(_) => "number",
+            ValueKind::String(..) => "string",
+            ValueKind::Table(_) => "table",
+            ValueKind::List(_) => "list",
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl std::ops::Index<&str> for Value {
+    type Output = Value;
+
+    /// Returns `null` for missing keys and non tables, like `serde_json`.
+    fn index(&self, key: &str) -> &Value {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl std::ops::Index<usize> for Value {
+    type Output = Value;
+
+    fn index(&self, index: usize) -> &Value {
+        self.as_list().and_then(|l| l.get(index)).unwrap_or(&NULL)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::new(ValueKind::Bool(b))
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Value {
+        Value::new(ValueKind::String(s.to_string(), StringKind::Normal))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::new(ValueKind::String(s, StringKind::Normal))
+    }
+}
+
+impl<T: Into<Number>> From<T> for Value {
+    fn from(n: T) -> Value {
+        Value::new(ValueKind::Number(n.into()))
+    }
+}
+
+impl From<Table> for Value {
+    fn from(t: Table) -> Value {
+        Value::new(ValueKind::Table(t))
+    }
+}
+
+impl From<List> for Value {
+    fn from(l: List) -> Value {
+        Value::new(ValueKind::List(l))
+    }
+}
+
+impl From<serde_json::Value> for Value {
+    fn from(value: serde_json::Value) -> Value {
+        Value::new(match value {
+            serde_json::Value::Null => ValueKind::Null,
+            serde_json::Value::Bool(b) => ValueKind::Bool(b),
+            serde_json::Value::Number(n) => ValueKind::Number(Number::new(n.to_string())),
+            serde_json::Value::String(s) => ValueKind::String(s, StringKind::Normal),
+            serde_json::Value::Array(a) => {
+                ValueKind::List(a.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(o) => ValueKind::Table(
+                o.into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect(),
+            ),
+        })
+    }
+}
+
+impl From<Number> for serde_json::Value {
+    fn from(n: Number) -> serde_json::Value {
+        if let Some(u) = n.as_u64() {
+            serde_json::Value::from(u)
+        } else if let Some(i) = n.as_i64() {
+            serde_json::Value::from(i)
+        } else {
+            n.as_f64()
+                .and_then(serde_json::Number::from_f64)
+                .map_or(serde_json::Value::Null, serde_json::Value::Number)
+        }
+    }
+}
+
+impl From<Value> for serde_json::Value {
+    fn from(value: Value) -> serde_json::Value {
+        match value.kind {
+            ValueKind::Null => serde_json::Value::Null,
+            ValueKind::Bool(b) => serde_json::Value::Bool(b),
+            ValueKind::Number(n) => n.into(),
+            ValueKind::String(s, _) => serde_json::Value::String(s),
+            ValueKind::List(l) => serde_json::Value::Array(l.into_iter().map(Into::into).collect()),
+            ValueKind::Table(t) => {
+                serde_json::Value::Object(t.into_iter().map(|e| (e.key, e.value.into())).collect())
+            }
+        }
+    }
+}
+
+impl serde::Serialize for Value {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+        use serde::ser::SerializeSeq;
+
+        match &self.kind {
+            ValueKind::Null => serializer.serialize_unit(),
+            ValueKind::Bool(b) => serializer.serialize_bool(*b),
+            ValueKind::Number(n) => {
+                if let Some(u) = n.as_u64() {
+                    serializer.serialize_u64(u)
+                } else if let Some(i) = n.as_i64() {
+                    serializer.serialize_i64(i)
+                } else {
+                    serializer.serialize_f64(n.as_f64().unwrap_or(f64::NAN))
+                }
+            }
+            ValueKind::String(s, _) => serializer.serialize_str(s),
+            ValueKind::List(l) => {
+                let mut seq = serializer.serialize_seq(Some(l.len()))?;
+
+                for value in l {
+                    seq.serialize_element(value)?;
+                }
+
+                seq.end()
+            }
+            ValueKind::Table(t) => {
+                let mut map = serializer.serialize_map(Some(t.len()))?;
+
+                for (key, value) in t.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Value {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("any MCL value")
+            }
+
+            fn visit_bool<E: serde::de::Error>(self, b: bool) -> Result<Value, E> {
+                Ok(b.into())
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, n: i64) -> Result<Value, E> {
+                Ok(n.into())
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, n: u64) -> Result<Value, E> {
+                Ok(n.into())
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, n: f64) -> Result<Value, E> {
+                Ok(n.into())
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Value, E> {
+                Ok(s.into())
+            }
+
+            fn visit_string<E: serde::de::Error>(self, s: String) -> Result<Value, E> {
+                Ok(s.into())
+            }
+
+            fn visit_unit<E: serde::de::Error>(self) -> Result<Value, E> {
+                Ok(Value::new(ValueKind::Null))
+            }
+
+            fn visit_none<E: serde::de::Error>(self) -> Result<Value, E> {
+                self.visit_unit()
+            }
+
+            fn visit_some<D: serde::Deserializer<'de>>(self, d: D) -> Result<Value, D::Error> {
+                serde::Deserialize::deserialize(d)
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Value, A::Error> {
+                let mut list = List::new();
+
+                while let Some(value) = seq.next_element()? {
+                    list.push(value);
+                }
+
+                Ok(list.into())
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Value, A::Error> {
+                let mut table = Table::new();
+
+                while let Some((key, value)) = map.next_entry::<String, Value>()? {
+                    table.insert(key, value);
+                }
+
+                Ok(table.into())
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+use std::process::Command;
+use std::process::Output;
+use std::process::Stdio;
+
+fn mcl(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_mcl"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_cli_to_json_and_back() {
+    let output = mcl(&["to-json"], "name \"app\"\nserver { port 8080 }\n");
+    let json = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+        serde_json::json!({ "name": "app", "server": { "port": 8080 } })
+    );
+
+    let output = mcl(&["from-json"], &json);
+    let back = String::from_utf8(output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        mcl::from_str::<serde_json::Value>(&back).unwrap(),
+        serde_json::json!({ "name": "app", "server": { "port": 8080 } })
+    );
+}
+
+#[test]
+fn test_cli_get() {
+    let source = "server { host \"localhost\" ports [80 443] }";
+
+    let output = mcl(&["get", "server.host"], source);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "localhost\n");
+
+    let output = mcl(&["get", "server.ports.1"], source);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "443\n");
+
+    let output = mcl(&["get", "server.missing"], source);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_cli_check() {
+    let output = mcl(&["check"], "foo 1\nbar 2\n");
+    assert!(output.status.success());
+
+    let output = mcl(&["check"], "foo {\n    bar baz\n}\nqux\n");
+    let report = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(report.contains(" --> <stdin>:2:9"));
+    assert!(report.contains("<stdin>: 2 errors found"));
+}
@@ -0,0 +1,85 @@
+use mcl::cst::Document;
+
+const SOURCE: &str = "// project settings
+mcl {
+    stars 0 // bump me
+    tags [\"config\"   \"lang\"]
+
+    /* keep */
+    owner \"jmmaa\"
+}
+";
+
+#[test]
+fn test_cst_round_trip() {
+    let doc = Document::parse(SOURCE).unwrap();
+
+    assert_eq!(doc.to_string(), SOURCE);
+    assert_eq!(doc.get("mcl.stars"), Some("0"));
+    assert_eq!(doc.get("mcl.tags.1"), Some("\"lang\""));
+}
+
+#[test]
+fn test_cst_set() {
+    let mut doc = Document::parse(SOURCE).unwrap();
+
+    doc.set("mcl.stars", &1).unwrap();
+    doc.set("mcl.tags.0", "cfg").unwrap();
+
+    assert_eq!(
+        doc.to_string(),
+        SOURCE
+            .replace("stars 0", "stars 1")
+            .replace("[\"config\"", "[\"cfg\"")
+    );
+
+    assert!(doc.set("mcl.forks", &1).is_err());
+}
+
+#[test]
+fn test_cst_insert_and_remove() {
+    let mut doc = Document::parse(SOURCE).unwrap();
+
+    doc.insert("mcl.forks", &2).unwrap();
+    doc.insert("license", "MIT").unwrap();
+
+    assert_eq!(
+        doc.to_string(),
+        SOURCE.replace("\"jmmaa\"\n", "\"jmmaa\"\n    forks 2\n") + "license \"MIT\"\n"
+    );
+
+    doc.remove("mcl.stars").unwrap();
+    doc.remove("mcl.tags.0").unwrap();
+
+    assert_eq!(
+        doc.to_string(),
+        SOURCE
+            .replace("    stars 0 // bump me\n", "")
+            .replace("\"config\"   ", "")
+            .replace("\"jmmaa\"\n", "\"jmmaa\"\n    forks 2\n")
+            + "license \"MIT\"\n"
+    );
+
+    let mut doc = Document::parse("server {}").unwrap();
+    doc.insert("server.port", &8080).unwrap();
+    assert_eq!(doc.to_string(), "server { port 8080 }");
+
+    assert!(doc.insert("server.port", &80).is_err());
+}
+
+#[test]
+fn test_cst_dotted_keys() {
+    let mut doc = Document::parse("server.http.port 8080\nserver.name \"web\"\n").unwrap();
+
+    assert_eq!(doc.get("server.http.port"), Some("8080"));
+    assert_eq!(doc.get("server.name"), Some("\"web\""));
+
+    doc.set("server.http.port", &9090).unwrap();
+    doc.insert("server.http.host", "localhost").unwrap();
+    doc.remove("server.name").unwrap();
+
+    assert_eq!(
+        doc.to_string(),
+        "server.http.port 9090\nserver.http.host \"localhost\"\n"
+    );
+}
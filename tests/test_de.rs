@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+
+use mcl::ErrorKind;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+    Debug,
+    Info,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+enum Backend {
+    File(String),
+    Tcp { host: String, port: u16 },
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Server {
+    host: String,
+    port: u16,
+    timeout: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    name: String,
+    level: Level,
+    server: Server,
+    backends: Vec<Backend>,
+    labels: BTreeMap<String, i32>,
+}
+
+#[test]
+fn test_struct() {
+    let config: Config = mcl::from_str(
+        r#"
+        name "app"
+        level "debug"
+
+        server {
+            host "localhost"
+            port 8080
+            timeout null
+        }
+
+        backends [
+            { File "/var/log/app.log" }
+            { Tcp { host "10.0.0.1" port 514 } }
+        ]
+
+        labels { a 1 "b c" -2 }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        config,
+        Config {
+            name: "app".to_string(),
+            level: Level::Debug,
+            server: Server {
+                host: "localhost".to_string(),
+                port: 8080,
+                timeout: None,
+            },
+            backends: vec![
+                Backend::File("/var/log/app.log".to_string()),
+                Backend::Tcp {
+                    host: "10.0.0.1".to_string(),
+                    port: 514,
+                },
+            ],
+            labels: BTreeMap::from([("a".to_string(), 1), ("b c".to_string(), -2)]),
+        }
+    );
+}
+
+#[test]
+fn test_root_list() {
+    let levels: Vec<Level> = mcl::from_str(r#""info" "debug""#).unwrap();
+
+    assert_eq!(levels, [Level::Info, Level::Debug]);
+
+    let numbers: Vec<f64> = mcl::from_str("[1 2.5]").unwrap();
+
+    assert_eq!(numbers, [1.0, 2.5]);
+}
+
+#[test]
+fn test_error_location() {
+    let err = mcl::from_str::<Server>("host \"localhost\"\nport \"80\"").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+
+    let loc = err.loc().unwrap();
+
+    assert_eq!((loc.start().line(), loc.start().column()), (2, 6));
+
+    let err = mcl::from_str::<Server>("host \"localhost\"\nprot 80").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnknownField);
+    assert_eq!(err.loc().unwrap().start().line(), 2);
+}
+
+#[test]
+fn test_missing_field() {
+    let err = mcl::from_str::<Config>("server {\n    host \"localhost\"\n}").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::MissingField);
+    assert_eq!(err.desc, "missing field 'port'");
+    assert_eq!(err.loc().unwrap().start().column(), 8);
+}
+
+#[test]
+fn test_from_file() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        port: u16,
+    }
+
+    let path = std::env::temp_dir().join(format!("mcl-from-file-{}.mcl", std::process::id()));
+    std::fs::write(&path, "\u{FEFF}port 8080").unwrap();
+
+    let server: Server = mcl::from_file(&path).unwrap();
+    std::fs::write(&path, "port \"http\"").unwrap();
+    let err = mcl::from_file::<Server>(&path).unwrap_err();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(server, Server { port: 8080 });
+    assert_eq!(err.file(), Some(path.display().to_string().as_str()));
+}
+
+#[test]
+fn test_deserializer() {
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        port: u16,
+    }
+
+    let mut deserializer = mcl::Deserializer::from_str("port 8080").unwrap();
+
+    assert_eq!(
+        Server::deserialize(&mut deserializer).unwrap(),
+        Server { port: 8080 }
+    );
+
+    let err = mcl::Deserializer::from_str("").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,36 @@
+use mcl::diagnostic::render;
+use serde_json::Value;
+
+#[test]
+fn test_render_parser_error() {
+    let source = "foo {\n\tbar baz\n}";
+    let err = mcl::from_str::<Value>(source).unwrap_err();
+
+    let expected = "\
+error: expected a value, found identifier 'baz'
+ --> config.mcl:2:6
+  |
+2 |     bar baz
+  |         ^^^
+";
+
+    assert_eq!(render("config.mcl", source.as_bytes(), &err), expected);
+}
+
+#[test]
+fn test_render_lexer_error() {
+    let source = "foo \"bar\nbaz 1";
+    let err = mcl::from_str::<Value>(source).unwrap_err();
+
+    let expected = "\
+error: cannot use newline character in strings
+ --> config.mcl:1:9
+  |
+1 | foo \"bar
+  |         ^
+  |
+  = help: use a `template string` or the '\\n' escape for multiline text
+";
+
+    assert_eq!(render("config.mcl", source.as_bytes(), &err), expected);
+}
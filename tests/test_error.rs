@@ -0,0 +1,223 @@
+use mcl::ErrorKind;
+use serde_json::Value;
+
+#[test]
+fn test_unterminated_string() {
+    let err = mcl::from_str::<Value>("foo {\n    bar \"baz\n}").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::NewlineInString);
+
+    let loc = err.loc().unwrap();
+
+    assert_eq!((loc.start().line(), loc.start().column()), (2, 13));
+}
+
+#[test]
+fn test_invalid_number() {
+    let err = mcl::from_str::<Value>("foo 1.x").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.loc().unwrap().start().column(), 7);
+}
+
+#[test]
+fn test_invalid_digit_separator() {
+    for (source, desc, column) in [
+        (
+            "size 1__000",
+            "digit separators cannot follow each other",
+            8,
+        ),
+        (
+            "size 1000_",
+            "digit separator must be followed with a digit",
+            10,
+        ),
+        (
+            "size 1_.5",
+            "digit separator must be followed with a digit",
+            7,
+        ),
+        ("mask 0x_FF", "digit separator must come after a digit", 8),
+        ("a 0_1", "leading zero cannot be followed with '_'", 4),
+        ("a 01", "leading zero cannot be followed with '1'", 4),
+    ] {
+        let err = mcl::from_str::<Value>(source).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+        assert_eq!(err.desc, desc);
+        assert_eq!(err.loc().unwrap().start().column(), column);
+    }
+}
+
+#[test]
+fn test_invalid_radix_number() {
+    let err = mcl::from_str::<Value>("mode 0o758").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+    assert_eq!(err.to_string(), "invalid digit '8' in octal number (1:10)");
+
+    let err = mcl::from_str::<Value>("big 1e").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+
+    let err = mcl::from_str::<Value>("big 0x10000000000000000").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidNumber);
+}
+
+#[test]
+fn test_unexpected_token() {
+    let err = mcl::from_str::<Value>("foo { bar baz }").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedToken);
+    assert_eq!(
+        err.to_string(),
+        "expected a value, found identifier 'baz' (1:11)"
+    );
+
+    let err = mcl::from_str::<Value>("foo").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::MissingValue);
+    assert_eq!(err.loc().unwrap().start().column(), 4);
+}
+
+#[test]
+fn test_unclosed_table() {
+    let err = mcl::from_str::<Value>("foo {\n    bar 1").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnclosedDelimiter);
+    assert_eq!(err.desc, "unclosed '{' opened at 1:5");
+
+    let loc = err.loc().unwrap();
+
+    assert_eq!((loc.start().line(), loc.start().column()), (2, 10));
+}
+
+#[test]
+fn test_unclosed_list() {
+    let err = mcl::from_str::<Value>("foo { bar [1 2 }").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnclosedDelimiter);
+    assert_eq!(err.to_string(), "unclosed '[' opened at 1:11 (1:11)");
+
+    let err = mcl::from_str::<Value>("foo [1 [2 3]").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnclosedDelimiter);
+    assert_eq!(err.desc, "unclosed '[' opened at 1:5");
+}
+
+#[test]
+fn test_unexpected_closing() {
+    let err = mcl::from_str::<Value>("foo 1 ]").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedDelimiter);
+    assert_eq!(err.desc, "unexpected closing ']'");
+    assert_eq!(err.loc().unwrap().start().column(), 7);
+
+    let err = mcl::from_str::<Value>("foo { bar 1 ] }").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedDelimiter);
+    assert_eq!(err.loc().unwrap().start().column(), 13);
+
+    let err = mcl::from_str::<Value>("{ foo 1 } }").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedDelimiter);
+    assert_eq!(err.loc().unwrap().start().column(), 11);
+}
+
+#[test]
+fn test_missing_value_before_closing() {
+    let err = mcl::from_str::<Value>("foo { bar }").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::MissingValue);
+}
+
+#[test]
+fn test_invalid_utf8() {
+    for (source, index, line, column) in [
+        (&b"name \"caf\xC3\""[..], 9, 1, 10),
+        (&b"// \xFF\nname \"cafe\""[..], 3, 1, 4),
+        (&b"/* \xE2\x82\xAC\n\xFF */ name 1"[..], 7, 2, 1),
+        (&b"name \xE9"[..], 5, 1, 6),
+    ] {
+        let err = mcl::from_slice::<Value>(source).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+        assert_eq!(
+            err.to_string(),
+            format!("invalid UTF-8 at byte {} ({}:{})", index, line, column)
+        );
+    }
+
+    let err = mcl::from_str::<Value>("price €").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedCharacter);
+    assert_eq!(err.to_string(), "unrecognized character '€' (1:7)");
+}
+
+#[test]
+fn test_invalid_escape() {
+    let strict = |source: &str| {
+        mcl::parser::Parser::new()
+            .with_strict_escapes(true)
+            .parse_slice(source.as_bytes())
+            .unwrap_err()
+    };
+
+    for (source, message) in [
+        (r#"path "C:\qux""#, "unknown escape '\\q' (1:9)"),
+        (
+            r#"max "\u{110000}""#,
+            "'110000' is not a unicode character (1:6)",
+        ),
+        (r#"half "\uD83D!""#, "unpaired surrogate '\\uD83D' (1:7)"),
+        (
+            "text `a\n  \\x9`",
+            "hex escape must have two hex digits (2:3)",
+        ),
+    ] {
+        let err = strict(source);
+
+        assert_eq!(err.kind(), ErrorKind::InvalidEscape);
+        assert_eq!(err.to_string(), message);
+    }
+
+    let output = mcl::from_str::<Value>(r#"path "C:\qux""#).unwrap();
+
+    assert_eq!(output["path"], "C:qux");
+}
+
+#[test]
+fn test_dotted_key_into_value() {
+    let err = mcl::from_str::<Value>("server { port 80 }\nserver.port.tls true").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidType);
+    assert_eq!(
+        err.to_string(),
+        "cannot set a key inside 'server.port', it is a number and not a table (2:8)"
+    );
+}
+
+#[test]
+fn test_duplicate_key() {
+    for (source, message) in [
+        (
+            "port 80\nport 8080",
+            "duplicate key 'port', first defined at 1:1 (2:1)",
+        ),
+        (
+            "server.http.port 80\nserver { http { port 8080 } }",
+            "duplicate key 'server.http.port', first defined at 1:13 (2:17)",
+        ),
+        (
+            "db { host \"a\" }\ndb { port 1 }",
+            "duplicate key 'db', first defined at 1:1 (2:1)",
+        ),
+    ] {
+        let err = mcl::from_str::<Value>(source).unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::DuplicateKey);
+        assert_eq!(err.to_string(), message);
+    }
+}
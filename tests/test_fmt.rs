@@ -0,0 +1,93 @@
+use mcl::lexer::Lexer;
+use mcl::FormatOptions;
+
+const SOURCE: &str = "// header
+
+name \"app\"   // the name
+\"version\" `1.0`
+server {
+  host \"localhost\"
+        port 8080 /* inline */
+
+
+  // nested
+  list [ 1 2 ]
+}
+// tail";
+
+#[test]
+fn test_trivia_round_trip() {
+    let tokens = Lexer::new()
+        .tokenize_with_trivia(SOURCE.as_bytes())
+        .unwrap();
+
+    let text: Vec<u8> = tokens
+        .iter()
+        .flat_map(|t| {
+            let loc = t.loc();
+            SOURCE.as_bytes()[loc.start().index()..loc.end().index()].to_vec()
+        })
+        .collect();
+
+    assert_eq!(String::from_utf8(text).unwrap(), SOURCE);
+}
+
+#[test]
+fn test_format() {
+    let expected = "// header
+
+name \"app\" // the name
+version `1.0`
+server {
+    host \"localhost\"
+    port 8080 /* inline */
+
+    // nested
+    list [1 2]
+}
+// tail
+";
+
+    let text = mcl::format(SOURCE.as_bytes(), &FormatOptions::default()).unwrap();
+
+    assert_eq!(text, expected);
+    assert_eq!(
+        mcl::format(text.as_bytes(), &FormatOptions::default()).unwrap(),
+        text
+    );
+}
+
+#[test]
+fn test_format_align_values() {
+    let options = FormatOptions {
+        indent: 2,
+        align_values: true,
+    };
+
+    let text = mcl::format(b"hostname \"a\" port 80\n\nlongest 1 x { y 2 }", &options).unwrap();
+
+    assert_eq!(
+        text,
+        "hostname \"a\"\nport     80\n\nlongest 1\nx {\n  y 2\n}\n"
+    );
+}
+
+#[test]
+fn test_format_without_resolving() {
+    let source = "@include \"db.mcl\"\nport $ref db.port\nname *app\n";
+
+    let text = mcl::format(source.as_bytes(), &FormatOptions::default()).unwrap();
+
+    assert_eq!(text, source);
+    assert!(mcl::cst::Document::parse(source).is_ok());
+}
+
+#[test]
+fn test_format_without_environment() {
+    let source = "url `postgres://${env:DB_HOST}/app`\nname `${missing}`\n";
+
+    let text = mcl::format(source.as_bytes(), &FormatOptions::default()).unwrap();
+
+    assert_eq!(text, source);
+    assert!(mcl::cst::Document::parse(source).is_ok());
+}
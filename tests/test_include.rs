@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use mcl::parser::Parser;
+use mcl::ErrorKind;
+
+fn files(files: &[(&str, &str)]) -> HashMap<PathBuf, String> {
+    files
+        .iter()
+        .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_include() {
+    let fs = files(&[
+        (
+            "config/main.mcl",
+            "name \"app\"\ndatabase { @include \"db/postgres.mcl\" }\n@include \"./common.mcl\"",
+        ),
+        (
+            "config/db/postgres.mcl",
+            "host \"localhost\"\nport 5432\n@include \"../common.mcl\"",
+        ),
+        ("config/common.mcl", "url `${name}.example.com`"),
+    ]);
+
+    let value = Parser::new()
+        .with_fs(fs)
+        .parse_file("config/main.mcl")
+        .unwrap();
+
+    assert_eq!(value.pointer("database.port").unwrap().as_u64(), Some(5432));
+    assert_eq!(
+        value.pointer("database.url").unwrap().as_str(),
+        Some("app.example.com")
+    );
+    assert_eq!(value["url"].as_str(), Some("app.example.com"));
+}
+
+#[test]
+fn test_include_errors() {
+    let fs = files(&[
+        ("a.mcl", "x 1\n@include \"b.mcl\""),
+        ("b.mcl", "y 2\n@include \"a.mcl\""),
+        ("broken.mcl", "ok 1\nport }"),
+        ("main.mcl", "@include \"broken.mcl\""),
+    ]);
+
+    let err = Parser::new()
+        .with_fs(fs.clone())
+        .parse_file("a.mcl")
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::IncludeCycle);
+    assert_eq!(err.desc, "include cycle: a.mcl -> b.mcl -> a.mcl");
+    assert_eq!(err.file(), Some("b.mcl"));
+    assert_eq!(err.loc().unwrap().start().line(), 2);
+
+    let err = Parser::new()
+        .with_fs(fs.clone())
+        .parse_file("main.mcl")
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::MissingValue);
+    assert_eq!(
+        err.to_string(),
+        "expected a value, found '}' (broken.mcl:2:6)"
+    );
+
+    let err = Parser::new()
+        .with_fs(fs)
+        .parse_slice(b"@include \"missing.mcl\"")
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Io);
+
+    let err = mcl::parse(b"@include \"a.mcl\"").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Io);
+    assert_eq!(
+        err.desc,
+        "cannot include 'a.mcl', no file system was given to the parser"
+    );
+}
+
+#[test]
+fn test_byte_order_mark() {
+    let fs = files(&[
+        ("main.mcl", "\u{FEFF}name \"app\"\n@include \"db.mcl\""),
+        ("db.mcl", "\u{FEFF}port 5432"),
+    ]);
+
+    let value = Parser::new().with_fs(fs).parse_file("main.mcl").unwrap();
+
+    assert_eq!(value["name"].as_str(), Some("app"));
+    assert_eq!(value["port"].as_u64(), Some(5432));
+
+    let source = "\u{FEFF}name \"app\"".as_bytes();
+    let err = mcl::parse(source).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnexpectedCharacter);
+    assert_eq!(
+        mcl::parse(mcl::strip_bom(source)).unwrap()["name"].as_str(),
+        Some("app")
+    );
+}
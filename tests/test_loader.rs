@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use mcl::loader::ConfigLoader;
+use mcl::merge::ListMerge;
+use mcl::ErrorKind;
+use serde::Deserialize;
+
+fn files() -> HashMap<PathBuf, String> {
+    [
+        (
+            "config/base.mcl",
+            "server { host \"0.0.0.0\" port 80 }\nfeatures [\"search\"]\nlog { level \"info\" }",
+        ),
+        (
+            "config/prod.mcl",
+            "server { port 443 }\nfeatures [\"billing\"]",
+        ),
+    ]
+    .into_iter()
+    .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+    .collect()
+}
+
+#[test]
+fn test_layers() {
+    let env = [
+        ("APP_LOG__LEVEL", "debug"),
+        ("APP_SERVER__WORKERS", "8"),
+        ("HOME", "/root"),
+    ];
+
+    let config = ConfigLoader::new()
+        .with_fs(files())
+        .file("config/base.mcl")
+        .file("config/prod.mcl")
+        .optional_file("config/local.mcl")
+        .env("APP_", env)
+        .load()
+        .unwrap();
+
+    let value = config.value();
+    assert_eq!(value.pointer("server.port").unwrap().as_u64(), Some(443));
+    assert_eq!(value.pointer("server.workers").unwrap().as_u64(), Some(8));
+    assert_eq!(value.pointer("log.level").unwrap().as_str(), Some("debug"));
+    assert!(value.get("home").is_none());
+
+    assert_eq!(config.origin("server.host"), Some("config/base.mcl"));
+    assert_eq!(config.origin("server.port"), Some("config/prod.mcl"));
+    assert_eq!(config.origin("features.0"), Some("config/prod.mcl"));
+    assert_eq!(config.origin("log.level"), Some("env"));
+
+    #[derive(Deserialize)]
+    struct Server {
+        host: String,
+        port: u16,
+        workers: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Config {
+        server: Server,
+    }
+
+    let config: Config = config.deserialize().unwrap();
+    assert_eq!(config.server.host, "0.0.0.0");
+    assert_eq!((config.server.port, config.server.workers), (443, 8));
+}
+
+#[test]
+fn test_layers_append() {
+    let config = ConfigLoader::new()
+        .with_fs(files())
+        .with_list_merge(ListMerge::Append)
+        .file("config/base.mcl")
+        .file("config/prod.mcl")
+        .string("overrides", "features [\"beta\"]")
+        .load()
+        .unwrap();
+
+    let features: Vec<&str> = config.value()["features"]
+        .as_list()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v.as_str())
+        .collect();
+
+    assert_eq!(features, ["search", "billing", "beta"]);
+    assert_eq!(config.origin("features.0"), Some("config/base.mcl"));
+    assert_eq!(config.origin("features.2"), Some("overrides"));
+}
+
+#[test]
+fn test_layer_errors() {
+    let err = ConfigLoader::new()
+        .with_fs(files())
+        .file("config/missing.mcl")
+        .load()
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::Io);
+
+    let err = ConfigLoader::new()
+        .with_fs(HashMap::from([(
+            PathBuf::from("broken.mcl"),
+            "port }".to_string(),
+        )]))
+        .file("broken.mcl")
+        .load()
+        .unwrap_err();
+    assert_eq!(err.file(), Some("broken.mcl"));
+
+    let err = ConfigLoader::new()
+        .string("overrides", "port }")
+        .load()
+        .unwrap_err();
+    assert_eq!(err.file(), Some("overrides"));
+
+    let err = ConfigLoader::new()
+        .string("list", "[1 2]")
+        .load()
+        .unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidType);
+}
@@ -0,0 +1,81 @@
+use mcl::merge::ListMerge;
+use mcl::parser::Parser;
+use mcl::ErrorKind;
+
+const SOURCE: &[u8] = b"
+base {
+    image \"app:1.0\"
+    replicas 1
+    tags [\"web\"]
+    resources { cpu 1 memory 512 }
+}
+prod extends base {
+    replicas 5
+    tags [\"prod\"]
+    resources { cpu 4 }
+}
+canary extends prod { replicas 1 }
+";
+
+#[test]
+fn test_extends() {
+    let mut parser = Parser::new();
+    let value = parser.parse_slice(SOURCE).unwrap();
+
+    let prod = &value["prod"];
+    assert_eq!(prod["image"].as_str(), Some("app:1.0"));
+    assert_eq!(prod["replicas"].as_u64(), Some(5));
+    assert_eq!(prod.pointer("resources.cpu").unwrap().as_u64(), Some(4));
+    assert_eq!(
+        prod.pointer("resources.memory").unwrap().as_u64(),
+        Some(512)
+    );
+    assert_eq!(prod["tags"].as_list().unwrap().len(), 1);
+
+    // base keys come first, in their original order
+    let keys: Vec<&str> = prod.as_table().unwrap().keys().collect();
+    assert_eq!(keys, ["image", "replicas", "tags", "resources"]);
+
+    let origins = parser.origins();
+    assert_eq!(origins.get("prod.image"), Some("base"));
+    assert_eq!(origins.get("prod.replicas"), Some("prod"));
+    assert_eq!(origins.get("prod.resources.memory"), Some("base"));
+    assert_eq!(origins.get("canary.resources.cpu"), Some("prod"));
+    assert_eq!(origins.get("canary.image"), Some("base"));
+    assert_eq!(origins.get("canary.replicas"), Some("canary"));
+}
+
+#[test]
+fn test_extends_append() {
+    let mut parser = Parser::new().with_list_merge(ListMerge::Append);
+    let value = parser.parse_slice(SOURCE).unwrap();
+
+    let tags: Vec<&str> = value
+        .pointer("canary.tags")
+        .unwrap()
+        .as_list()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v.as_str())
+        .collect();
+
+    assert_eq!(tags, ["web", "prod"]);
+    assert_eq!(parser.origins().get("canary.tags.0"), Some("base"));
+    assert_eq!(parser.origins().get("canary.tags.1"), Some("prod"));
+}
+
+#[test]
+fn test_extends_errors() {
+    let err = mcl::parse(b"prod extends missing { replicas 5 }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnresolvedReference);
+
+    let err = mcl::parse(b"a extends b { x 1 }\nb extends a { y 1 }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+
+    let err = mcl::parse(b"a [1 2]\nb extends a { y 1 }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidType);
+
+    // still a plain key when not followed by a base and a table
+    let value = mcl::parse(b"extends 1\nx { extends { y 2 } }").unwrap();
+    assert_eq!(value["extends"].as_u64(), Some(1));
+}
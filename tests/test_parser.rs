@@ -4,14 +4,14 @@ use serde_json::Value;
 fn test_from_slice() {
     let file = std::fs::read("./tests/sample.mcl").unwrap();
 
-    let output = mcl::from_slice(&file).unwrap();
+    let output = mcl::from_slice::<Value>(&file).unwrap();
 
     assert!(output["jmmaa"] != Value::Null);
 }
 
 #[test]
 fn test_from_str() {
-    let output = mcl::from_str(r#"foo { bar "baz" }"#).unwrap();
+    let output = mcl::from_str::<Value>(r#"foo { bar "baz" }"#).unwrap();
 
     let num = &output["foo"]["bar"];
 
@@ -24,7 +24,7 @@ fn test_from_str() {
 
 #[test]
 fn test_arr() {
-    let output = mcl::from_str("\"marky\" 32 23.23 null").unwrap();
+    let output = mcl::from_str::<Value>("\"marky\" 32 23.23 null").unwrap();
 
     assert!(&output[0].is_string());
     assert!(&output[1].is_number());
@@ -35,3 +35,149 @@ fn test_arr() {
 
     assert!(val == Some(23.23));
 }
+
+#[test]
+fn test_braced_root() {
+    let output = mcl::from_str::<Value>("{ foo [1 2] }").unwrap();
+
+    assert!(output["foo"][1] == 2);
+}
+
+#[test]
+fn test_number_literals() {
+    let output = mcl::from_str::<Value>(
+        "exp 1e6 small 2.5E-3 big -1.5e+3 hex 0xFF mode 0o755 mask 0b1010 neg -0x10",
+    )
+    .unwrap();
+
+    assert!(output["exp"].as_f64() == Some(1e6));
+    assert!(output["small"].as_f64() == Some(0.0025));
+    assert!(output["big"].as_f64() == Some(-1500.0));
+    assert!(output["hex"].as_u64() == Some(255));
+    assert!(output["mode"].as_u64() == Some(0o755));
+    assert!(output["mask"].as_u64() == Some(10));
+    assert!(output["neg"].as_i64() == Some(-16));
+}
+
+#[test]
+fn test_number_literals_keep_their_text() {
+    let output = mcl::parse(b"hex 0xFF neg -0x10 size 1_000").unwrap();
+
+    let hex = output["hex"].as_number().unwrap();
+    assert_eq!(hex.as_str(), "0xFF");
+    assert_eq!(hex.as_u64(), Some(255));
+    assert_eq!(hex.as_f64(), Some(255.0));
+
+    let neg = output["neg"].as_number().unwrap();
+    assert_eq!(neg.as_str(), "-0x10");
+    assert_eq!((neg.as_i64(), neg.as_u64()), (Some(-16), None));
+
+    let size = output["size"].as_number().unwrap();
+    assert_eq!((size.as_str(), size.as_u64()), ("1_000", Some(1000)));
+}
+
+#[test]
+fn test_digit_separators() {
+    let output =
+        mcl::from_str::<Value>("size 1_048_576 mask 0xFFFF_FFFF ratio 1_000.000_1e1_0").unwrap();
+
+    assert!(output["size"].as_u64() == Some(1_048_576));
+    assert!(output["mask"].as_u64() == Some(0xFFFF_FFFF));
+    assert!(output["ratio"].as_f64() == Some(1_000.000_1e10));
+}
+
+#[test]
+fn test_unicode_escapes() {
+    let output = mcl::from_str::<Value>(
+        r#"emoji "\u{1F600}" pair "\uD83D\uDE00" e "caf\u00E9" tab "a\x09b" path "C:\qux" text `\u{48}i ${emoji}`"#,
+    )
+    .unwrap();
+
+    assert!(output["emoji"] == "😀");
+    assert!(output["pair"] == "😀");
+    assert!(output["e"] == "café");
+    assert!(output["tab"] == "a\tb");
+    assert!(output["path"] == "C:qux");
+    assert!(output["text"] == "Hi 😀");
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    let output =
+        mcl::from_str::<Value>("max-connections 10 _private true ключ \"значение\" a--b 1 c -1")
+            .unwrap();
+
+    assert!(output["max-connections"] == 10);
+    assert!(output["_private"] == true);
+    assert!(output["ключ"] == "значение");
+    assert!(output["a--b"] == 1);
+    assert!(output["c"] == -1);
+}
+
+#[test]
+fn test_dotted_keys() {
+    let output = mcl::from_str::<Value>(
+        "server.http.port 8080 server { host \"0.0.0.0\" http { tls true } } server.name \"web\" \
+         db { pool { max 10 } } db.pool.min 1 \"api.v2\" true",
+    )
+    .unwrap();
+
+    assert!(
+        output
+            == serde_json::json!({
+                "server": { "http": { "port": 8080, "tls": true }, "host": "0.0.0.0", "name": "web" },
+                "db": { "pool": { "max": 10, "min": 1 } },
+                "api.v2": true,
+            })
+    );
+
+    let value = mcl::parse(b"server.http.port 8080").unwrap();
+    let http = value
+        .pointer("server")
+        .unwrap()
+        .as_table()
+        .unwrap()
+        .entry("http");
+
+    assert!(http.unwrap().key_loc().unwrap().start().column() == 8);
+    assert!(
+        value
+            .pointer("server.http.port")
+            .unwrap()
+            .loc()
+            .unwrap()
+            .start()
+            .column()
+            == 18
+    );
+}
+
+#[test]
+fn test_duplicate_keys() {
+    use mcl::merge::DuplicateKeys;
+    use mcl::parser::Parser;
+
+    let source = b"b 1 a `x${b}` db { host \"a\" tags [1] } a \"y\" db { port 2 tags [2] }";
+
+    let parse = |duplicates| {
+        let value = Parser::new()
+            .with_duplicate_keys(duplicates)
+            .parse_slice(source)
+            .unwrap();
+
+        serde_json::to_value(&value).unwrap()
+    };
+
+    assert!(
+        parse(DuplicateKeys::LastWins)
+            == serde_json::json!({ "b": 1, "a": "y", "db": { "port": 2, "tags": [2] } })
+    );
+    assert!(
+        parse(DuplicateKeys::FirstWins)
+            == serde_json::json!({ "b": 1, "a": "x1", "db": { "host": "a", "tags": [1] } })
+    );
+    assert!(
+        parse(DuplicateKeys::MergeTables)
+            == serde_json::json!({ "b": 1, "a": "y", "db": { "host": "a", "tags": [2], "port": 2 } })
+    );
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use mcl::lexer::Lexer;
+use mcl::parser::Parser;
+use mcl::ErrorKind;
+use serde_json::json;
+
+#[test]
+fn test_reports_every_error() {
+    let (output, errors) = mcl::from_str_recovering(
+        r#"
+        server {
+            host "localhost"
+            port
+        }
+
+        database {
+            user "admin"
+            pool { size x min 1 }
+            name "main
+        }
+
+        debug true
+        "#,
+    );
+
+    let kinds: Vec<_> = errors.iter().map(|e| e.kind()).collect();
+
+    assert_eq!(
+        kinds,
+        [
+            ErrorKind::MissingValue,
+            ErrorKind::UnexpectedToken,
+            ErrorKind::NewlineInString,
+        ]
+    );
+
+    let lines: Vec<_> = errors
+        .iter()
+        .map(|e| e.loc().unwrap().start().line())
+        .collect();
+
+    assert_eq!(lines, [5, 9, 10]);
+
+    assert_eq!(
+        serde_json::Value::from(output),
+        json!({
+            "server": { "host": "localhost" },
+            "database": { "user": "admin", "pool": { "min": 1 }, "name": "main" },
+            "debug": true
+        })
+    );
+}
+
+#[test]
+fn test_no_errors() {
+    let (output, errors) = mcl::from_str_recovering("foo [1 2 3]");
+
+    assert!(errors.is_empty());
+    assert_eq!(serde_json::Value::from(output), json!({ "foo": [1, 2, 3] }));
+}
+
+#[test]
+fn test_unbalanced_delimiters() {
+    let (output, errors) = mcl::from_str_recovering("foo { bar 1 ] baz [1 2 }\nqux 3");
+
+    let kinds: Vec<_> = errors.iter().map(|e| e.kind()).collect();
+
+    assert_eq!(
+        kinds,
+        [ErrorKind::UnexpectedDelimiter, ErrorKind::UnclosedDelimiter]
+    );
+    assert_eq!(errors[1].desc, "unclosed '[' opened at 1:19");
+
+    assert_eq!(output["foo"]["bar"].as_i64(), Some(1));
+    assert_eq!(output["foo"]["baz"][1].as_i64(), Some(2));
+    assert_eq!(output["qux"].as_i64(), Some(3));
+}
+
+#[test]
+fn test_invalid_utf8_string() {
+    let (output, errors) = mcl::from_slice_recovering(b"a \"\xff\"\nb 1\nc `\xfe ${b}`");
+
+    let kinds: Vec<_> = errors.iter().map(|e| e.kind()).collect();
+
+    assert_eq!(kinds, [ErrorKind::InvalidUtf8, ErrorKind::InvalidUtf8]);
+    assert_eq!(output["b"].as_i64(), Some(1));
+}
+
+#[test]
+fn test_failed_include() {
+    let (_, errors) = mcl::from_str_recovering("@include \"missing.mcl\"\nz 1\nz 2\nq [1");
+
+    let kinds: Vec<_> = errors.iter().map(|e| e.kind()).collect();
+
+    assert_eq!(
+        kinds,
+        [
+            ErrorKind::Io,
+            ErrorKind::DuplicateKey,
+            ErrorKind::UnclosedDelimiter,
+        ]
+    );
+
+    let fs = HashMap::from([(PathBuf::from("empty.mcl"), String::new())]);
+    let source = "x &a 1\n@include \"empty.mcl\"\ny *a";
+    let tokens = Lexer::new().tokenize(source.as_bytes()).unwrap();
+
+    let (output, errors) = Parser::new().with_fs(fs).parse_recovering(&tokens);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].file(), Some("empty.mcl"));
+    assert_eq!(serde_json::Value::from(output), json!({ "x": 1, "y": 1 }));
+}
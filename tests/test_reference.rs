@@ -0,0 +1,69 @@
+use mcl::ErrorKind;
+
+#[test]
+fn test_anchor_alias() {
+    let value = mcl::parse(
+        b"
+        name \"app\"
+        defaults {
+            retry &default_retry { attempts 3 backoff `${name}-backoff` }
+        }
+        jobs [
+            { retry *default_retry }
+            { retry *default_retry }
+        ]
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(
+        value.pointer("jobs.1.retry.attempts").unwrap().as_u64(),
+        Some(3)
+    );
+    assert_eq!(
+        value.pointer("jobs.0.retry.backoff").unwrap().as_str(),
+        Some("app-backoff")
+    );
+    assert_eq!(
+        value.pointer("jobs.0.retry"),
+        value.pointer("defaults.retry")
+    );
+}
+
+#[test]
+fn test_ref() {
+    let value = mcl::parse(
+        b"
+        worker { retry $ref defaults.retry }
+        defaults { retry { attempts 3 } }
+        fallback $ref worker.retry.attempts
+        ",
+    )
+    .unwrap();
+
+    assert_eq!(
+        value.pointer("worker.retry.attempts").unwrap().as_u64(),
+        Some(3)
+    );
+    assert_eq!(value["fallback"].as_u64(), Some(3));
+
+    // the copy points at the reference that made it
+    let loc = value.pointer("worker.retry").unwrap().loc().unwrap();
+    assert_eq!((loc.start().line(), loc.start().column()), (2, 24));
+}
+
+#[test]
+fn test_reference_errors() {
+    let err = mcl::parse(b"a *missing").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnresolvedReference);
+
+    let err = mcl::parse(b"a $ref b.c\nb { d 1 }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::UnresolvedReference);
+    assert_eq!(err.loc().unwrap().start().column(), 3);
+
+    let err = mcl::parse(b"a { b $ref c }\nc { d $ref a }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+
+    let err = mcl::parse(b"a &x { b *x }").unwrap_err();
+    assert_eq!(err.kind, ErrorKind::ReferenceCycle);
+}
@@ -0,0 +1,84 @@
+use mcl::schema::Schema;
+use mcl::ErrorKind;
+
+const SCHEMA: &str = r#"
+port { type "integer" min 1 max 65535 }
+hosts { type "list" min_len 1 items "string" }
+log {
+    optional true
+    deny_unknown true
+    fields {
+        level { one_of ["debug" "info" "warn"] }
+        file { type ["string" "null"] pattern "\\.log$" optional true }
+    }
+}
+"#;
+
+#[test]
+fn test_schema_valid() {
+    let schema: Schema = SCHEMA.parse().unwrap();
+
+    let value = mcl::parse(b"port 8080 hosts [\"a\" \"b\"] log { level \"info\" }").unwrap();
+    assert!(schema.validate(&value).is_empty());
+
+    let value = mcl::parse(b"port 1 hosts [\"a\"]").unwrap();
+    assert!(schema.validate(&value).is_empty());
+}
+
+#[test]
+fn test_schema_violations() {
+    let schema: Schema = SCHEMA.parse().unwrap();
+
+    let source = b"port 70000
+hosts [\"a\" 2]
+log {
+    level \"trace\"
+    file \"out.txt\"
+    color true
+}";
+
+    let errors = schema.validate(&mcl::parse(source).unwrap());
+    let found: Vec<(&str, usize, usize)> = errors
+        .iter()
+        .map(|e| {
+            let start = e.loc().unwrap().start();
+            (e.desc.as_str(), start.line(), start.column())
+        })
+        .collect();
+
+    assert!(errors.iter().all(|e| e.kind == ErrorKind::SchemaViolation));
+    assert_eq!(
+        found,
+        [
+            ("'port' must be at most 65535, found 70000", 1, 6),
+            ("'hosts.1' must be a string, found a number", 2, 12),
+            (
+                "'log.level' must be one of \"debug\", \"info\", \"warn\"",
+                4,
+                11
+            ),
+            ("'log.file' must match the pattern '\\.log$'", 5, 10),
+            ("unknown key 'log.color'", 6, 5),
+        ]
+    );
+
+    let errors = schema.validate(&mcl::parse(b"hosts []").unwrap());
+    let descs: Vec<&str> = errors.iter().map(|e| e.desc.as_str()).collect();
+    assert_eq!(
+        descs,
+        [
+            "missing required key 'port'",
+            "'hosts' must have at least 1 item, found 0"
+        ]
+    );
+}
+
+#[test]
+fn test_invalid_schema() {
+    let err = "port { type \"int\" }".parse::<Schema>().unwrap_err();
+    assert_eq!(err.kind, ErrorKind::InvalidSchema);
+    assert_eq!(err.loc().unwrap().start().column(), 13);
+
+    let err = "port { maximum 5 }".parse::<Schema>().unwrap_err();
+    assert_eq!(err.desc, "unknown constraint 'maximum'");
+}
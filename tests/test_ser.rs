@@ -0,0 +1,144 @@
+use std::collections::BTreeMap;
+
+use mcl::ErrorKind;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+enum Backend {
+    Stdout,
+    File(String),
+    Tcp { host: String, port: u16 },
+    Pair(u8, u8),
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+struct Config {
+    name: String,
+    ratio: f64,
+    motd: String,
+    tags: Vec<String>,
+    backends: Vec<Backend>,
+    labels: BTreeMap<String, Option<i64>>,
+}
+
+fn config() -> Config {
+    Config {
+        name: "say \"hi\"".to_string(),
+        ratio: 1.0,
+        motd: "line one\nline `two`".to_string(),
+        tags: vec![],
+        backends: vec![
+            Backend::Stdout,
+            Backend::File("C:\\log".to_string()),
+            Backend::Tcp {
+                host: "localhost".to_string(),
+                port: 514,
+            },
+            Backend::Pair(1, 2),
+        ],
+        labels: BTreeMap::from([
+            ("plain".to_string(), Some(-1)),
+            ("with space".to_string(), None),
+        ]),
+    }
+}
+
+#[test]
+fn test_to_string() {
+    let text = mcl::to_string(&config()).unwrap();
+
+    assert_eq!(
+        text,
+        "name \"say \\\"hi\\\"\" ratio 1.0 motd `line one\nline \\`two\\`` tags [] \
+         backends [\"Stdout\" { File \"C:\\\\log\" } { Tcp { host \"localhost\" port 514 } } \
+         { Pair [1 2] }] labels { plain -1 \"with space\" null }"
+    );
+
+    assert_eq!(mcl::from_str::<Config>(&text).unwrap(), config());
+}
+
+#[test]
+fn test_to_string_pretty() {
+    let text = mcl::to_string_pretty(&json!({
+        "server": { "host": "localhost", "ports": [80, 443] },
+        "empty": {}
+    }))
+    .unwrap();
+
+    assert_eq!(
+        text,
+        r#"empty {}
+server {
+    host "localhost"
+    ports [
+        80
+        443
+    ]
+}"#
+    );
+
+    assert_eq!(
+        mcl::from_str::<Config>(&mcl::to_string_pretty(&config()).unwrap()).unwrap(),
+        config()
+    );
+}
+
+#[test]
+fn test_json_round_trip() {
+    for value in [
+        json!({ "a": 1, "b": [true, null, 2.5, "x"] }),
+        json!({ "quoted key": { "nested": -3 } }),
+        json!({}),
+        json!([{ "a": 1 }, [2]]),
+    ] {
+        let text = mcl::to_string(&value).unwrap();
+
+        assert_eq!(mcl::from_str::<Value>(&text).unwrap(), value, "{}", text);
+    }
+}
+
+#[test]
+fn test_scalar_document() {
+    for err in [
+        mcl::to_string(&5).unwrap_err(),
+        mcl::to_string("text").unwrap_err(),
+        mcl::to_string(&Option::<u8>::None).unwrap_err(),
+    ] {
+        assert_eq!(err.kind(), ErrorKind::InvalidType);
+    }
+
+    assert_eq!(mcl::to_string(&[5]).unwrap(), "[5]");
+}
+
+#[test]
+fn test_to_writer() {
+    let mut out = Vec::new();
+
+    mcl::to_writer(&mut out, &json!({ "a": "b" })).unwrap();
+
+    assert_eq!(out, b"a \"b\"");
+}
+
+#[test]
+fn test_bare_keys() {
+    let value = json!({
+        "max-connections": 1,
+        "_private": 2,
+        "ключ": 3,
+        "-dash": 4,
+        "dash-": 5,
+        "api.v2": 6,
+        "null": 7,
+    });
+
+    let text = mcl::to_string(&value).unwrap();
+
+    assert_eq!(
+        text,
+        "{ \"-dash\" 4 _private 2 \"api.v2\" 6 \"dash-\" 5 max-connections 1 \"null\" 7 ключ 3 }"
+    );
+    assert_eq!(mcl::from_str::<Value>(&text).unwrap(), value);
+}
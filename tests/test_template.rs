@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use mcl::parser::Parser;
+use mcl::value::Table;
+use mcl::ErrorKind;
+use mcl::Value;
+
+#[test]
+fn test_interpolation() {
+    let source = "
+url `http://${server.host}:${server.port}/${name}`
+server { host \"localhost\" port 8080 }
+name `app-${version}`
+version \"1.0\"
+escaped `\\${name}`
+";
+
+    let value = mcl::parse(source.as_bytes()).unwrap();
+
+    assert_eq!(value["url"].as_str(), Some("http://localhost:8080/app-1.0"));
+    assert_eq!(value["name"].as_str(), Some("app-1.0"));
+    assert_eq!(value["escaped"].as_str(), Some("${name}"));
+}
+
+#[test]
+fn test_interpolation_quoted_keys() {
+    let source = "x { \"a.b\" `${y}` } y 1 z { c $ref x }";
+
+    let value = mcl::parse(source.as_bytes()).unwrap();
+
+    assert_eq!(value["x"]["a.b"].as_str(), Some("1"));
+    assert_eq!(value["z"]["c"]["a.b"].as_str(), Some("1"));
+}
+
+#[test]
+fn test_interpolation_variables() {
+    let mut variables = Table::new();
+    variables.insert("user", Value::from("root"));
+
+    let value =
+        mcl::parse_with_variables(b"home `/home/${user}` user_dir `${home}`", variables).unwrap();
+
+    assert_eq!(value["home"].as_str(), Some("/home/root"));
+    assert_eq!(value["user_dir"].as_str(), Some("/home/root"));
+}
+
+#[test]
+fn test_interpolation_errors() {
+    let err = mcl::parse(b"a `x ${missing} y`").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnresolvedReference);
+    assert_eq!(err.desc, "cannot resolve '${missing}'");
+    assert_eq!(err.loc().unwrap().start().column(), 6);
+    assert_eq!(err.loc().unwrap().end().column(), 16);
+
+    let err = mcl::parse(b"a `${b}`\nb `-${c}`\nc `${a}`").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::ReferenceCycle);
+    assert_eq!(err.desc, "cyclic reference: a -> b -> c -> a");
+    assert_eq!(err.loc().unwrap().start().line(), 3);
+
+    let err = mcl::parse(b"a `${b`").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidPlaceholder);
+}
+
+#[test]
+fn test_env_substitution() {
+    let env: HashMap<String, String> = [("HOST", "db.local"), ("PORT", "")]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+    let source = b"url `${env:HOST}:${env:PORT:-5432}`\nsecret `${env:SECRET}`";
+
+    let err = Parser::new()
+        .with_env(env.clone())
+        .parse_slice(source)
+        .unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::MissingEnvVar);
+    assert_eq!(err.desc, "environment variable 'SECRET' is not set");
+    assert_eq!(err.loc().unwrap().start().line(), 2);
+    assert_eq!(err.loc().unwrap().start().column(), 9);
+
+    let value = Parser::new()
+        .with_env(move |name: &str| match name {
+            "SECRET" => Some("hunter2".to_string()),
+            _ => env.get(name).cloned(),
+        })
+        .parse_slice(source)
+        .unwrap();
+
+    assert_eq!(value["url"].as_str(), Some("db.local:5432"));
+    assert_eq!(value["secret"].as_str(), Some("hunter2"));
+
+    // substitution is opt-in
+    let err = mcl::parse(source).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UnresolvedReference);
+}
+
+#[test]
+fn test_env_empty_value() {
+    let env = HashMap::from([("EMPTY".to_string(), String::new())]);
+
+    let value = Parser::new()
+        .with_env(env)
+        .parse_slice(b"plain `[${env:EMPTY}]` fallback `[${env:EMPTY:-none}]`")
+        .unwrap();
+
+    assert_eq!(value["plain"].as_str(), Some("[]"));
+    assert_eq!(value["fallback"].as_str(), Some("[none]"));
+}
@@ -0,0 +1,68 @@
+use mcl::value::StringKind;
+use mcl::Value;
+use serde_json::json;
+
+#[test]
+fn test_key_order() {
+    let value: Value = "zeta 1 alpha 2 mid { b 1 a 2 }".parse().unwrap();
+
+    let keys: Vec<_> = value.as_table().unwrap().keys().collect();
+
+    assert_eq!(keys, ["zeta", "alpha", "mid"]);
+
+    let keys: Vec<_> = value["mid"].as_table().unwrap().keys().collect();
+
+    assert_eq!(keys, ["b", "a"]);
+}
+
+#[test]
+fn test_exact_numbers_and_strings() {
+    let value: Value = "big 18446744073709551615 ratio 1.50 neg -7 a \"x\" b `y`"
+        .parse()
+        .unwrap();
+
+    assert_eq!(value["big"].as_u64(), Some(u64::MAX));
+    assert_eq!(value["ratio"].as_number().unwrap().as_str(), "1.50");
+    assert_eq!(value["neg"].as_i64(), Some(-7));
+    assert_eq!(value["a"].string_kind(), Some(StringKind::Normal));
+    assert_eq!(value["b"].string_kind(), Some(StringKind::Template));
+}
+
+#[test]
+fn test_spans() {
+    let value: Value = "server {\n    port 8080\n    hosts [\"a\" \"b\"]\n}"
+        .parse()
+        .unwrap();
+
+    let server = value.as_table().unwrap().entry("server").unwrap();
+    let key = server.key_loc().unwrap();
+
+    assert_eq!((key.start().line(), key.start().column()), (1, 1));
+
+    let table = server.value().loc().unwrap();
+
+    assert_eq!((table.start().line(), table.start().column()), (1, 8));
+    assert_eq!((table.end().line(), table.end().column()), (4, 2));
+
+    let port = value.pointer("server.port").unwrap().loc().unwrap();
+
+    assert_eq!((port.start().column(), port.end().column()), (10, 14));
+
+    let b = value.pointer("server.hosts.1").unwrap().loc().unwrap();
+
+    assert_eq!((b.start().line(), b.start().column()), (3, 16));
+}
+
+#[test]
+fn test_json_conversion() {
+    let json = json!({ "a": [1, -2, 2.5, "s", null, true], "b": { "c": {} } });
+
+    let value = Value::from(json.clone());
+
+    assert_eq!(value["a"][2].as_f64(), Some(2.5));
+    assert_eq!(serde_json::Value::from(value.clone()), json);
+
+    let parsed: Value = r#"a [1 -2 2.5 "s" null true] b { c {} }"#.parse().unwrap();
+
+    assert_eq!(parsed, value);
+}